@@ -0,0 +1,349 @@
+use std::ascii;
+use std::cmp;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::net;
+use std::os::unix::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str::{self, FromStr};
+
+use libc;
+
+use cvt;
+use error::{self, SocketError};
+use socket::{self, sockaddr_un, sun_path_offset};
+
+/// An address associated with a Unix socket.
+///
+/// Unlike the standard library's `SocketAddr`, values of this type can be
+/// constructed directly, including the Linux-specific abstract addresses
+/// which don't correspond to any file on the filesystem.
+///
+/// Abstract addresses are displayed, and can be parsed from strings, with a
+/// leading `@` standing in for the nul byte which starts them. A relative
+/// pathname which itself starts with `@` is displayed the same way, so it
+/// has to be written as `./@name` to be parsed back as a pathname. Bytes of
+/// abstract names which aren't printable UTF-8 are displayed as `\xNN`
+/// escapes, and backslashes as `\\`, which parsing turns back into the
+/// original bytes.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct UnixSocketAddr {
+    kind: Kind,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Kind {
+    Unnamed,
+    Pathname(PathBuf),
+    Abstract(Vec<u8>),
+}
+
+impl UnixSocketAddr {
+    /// Creates an address referring to the socket file at `path`.
+    ///
+    /// Returns an error if the path is empty, contains a nul byte, or is too
+    /// long to fit in a `sockaddr_un`. On Linux, longer paths are accepted as
+    /// they are by `UnixListener::bind` and `UnixStream::connect`, as long as
+    /// their file name fits: the socket is then reached through its
+    /// directory, which is opened whenever the address is used.
+    pub fn from_pathname<P: AsRef<Path>>(path: P) -> io::Result<UnixSocketAddr> {
+        let path = path.as_ref();
+        let bytes = path.as_os_str().as_bytes();
+        match bytes.first() {
+            None => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                   "pathname addresses must not be empty"))
+            }
+            Some(&0) => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                   "pathname addresses must not start with a nul byte"))
+            }
+            Some(_) if bytes.contains(&0) => {
+                Err(SocketError::InteriorNul { path: path.to_path_buf() }.into())
+            }
+            Some(_) => {
+                socket::check_pathname_len(path)?;
+                Ok(UnixSocketAddr { kind: Kind::Pathname(path.to_path_buf()) })
+            }
+        }
+    }
+
+    /// Creates an address in the abstract namespace with the given name.
+    ///
+    /// The name may contain arbitrary bytes, including nul bytes. Returns an
+    /// error if it's too long to fit in a `sockaddr_un`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn from_abstract(name: &[u8]) -> io::Result<UnixSocketAddr> {
+        let addr = UnixSocketAddr { kind: Kind::Abstract(name.to_vec()) };
+        addr.to_raw()?;
+        Ok(addr)
+    }
+
+    /// Creates an unnamed address, as reported for sockets which aren't bound
+    /// to anything.
+    pub fn unnamed() -> UnixSocketAddr {
+        UnixSocketAddr { kind: Kind::Unnamed }
+    }
+
+    /// Returns whether this is an unnamed address.
+    pub fn is_unnamed(&self) -> bool {
+        matches!(self.kind, Kind::Unnamed)
+    }
+
+    /// Returns the path of the socket file if this is a pathname address.
+    pub fn as_pathname(&self) -> Option<&Path> {
+        match self.kind {
+            Kind::Pathname(ref path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns the name, without the leading nul byte, if this is an address
+    /// in the abstract namespace.
+    pub fn as_abstract(&self) -> Option<&[u8]> {
+        match self.kind {
+            Kind::Abstract(ref name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Parses an address filled in by the kernel.
+    pub(crate) fn from_raw(addr: &libc::sockaddr_un, len: libc::socklen_t) -> UnixSocketAddr {
+        let len = (len as usize).saturating_sub(sun_path_offset());
+        let len = cmp::min(len, addr.sun_path.len());
+        let path = unsafe {
+            &*(&addr.sun_path[..len] as *const [libc::c_char] as *const [u8])
+        };
+        let kind = match path.first() {
+            None => Kind::Unnamed,
+            Some(&0) if cfg!(any(target_os = "linux", target_os = "android")) => {
+                Kind::Abstract(path[1..].to_vec())
+            }
+            Some(&0) => Kind::Unnamed,
+            Some(_) => {
+                let end = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+                Kind::Pathname(PathBuf::from(OsStr::from_bytes(&path[..end])))
+            }
+        };
+        UnixSocketAddr { kind }
+    }
+
+    /// Returns the raw form of this address to pass to the kernel, along
+    /// with the directory the address goes through if it's a pathname too
+    /// long to fit.
+    ///
+    /// The address is only valid while that directory stays open.
+    pub(crate) fn to_raw(&self)
+        -> io::Result<(libc::sockaddr_un, libc::socklen_t, Option<OwnedFd>)>
+    {
+        unsafe {
+            match self.kind {
+                Kind::Unnamed => {
+                    let mut addr: libc::sockaddr_un = mem::zeroed();
+                    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+                    Ok((addr, sun_path_offset() as libc::socklen_t, None))
+                }
+                Kind::Pathname(ref path) => socket::sockaddr_un_holding_dir(path),
+                Kind::Abstract(ref name) => {
+                    let mut bytes = Vec::with_capacity(name.len() + 1);
+                    bytes.push(0);
+                    bytes.extend_from_slice(name);
+                    let (addr, len) = sockaddr_un(Path::new(OsStr::from_bytes(&bytes)))?;
+                    Ok((addr, len, None))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn abstract_name(addr: &net::SocketAddr) -> Option<&[u8]> {
+    use std::os::linux::net::SocketAddrExt;
+    addr.as_abstract_name()
+}
+
+#[cfg(target_os = "android")]
+fn abstract_name(addr: &net::SocketAddr) -> Option<&[u8]> {
+    use std::os::android::net::SocketAddrExt;
+    addr.as_abstract_name()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn abstract_name(_addr: &net::SocketAddr) -> Option<&[u8]> {
+    None
+}
+
+/// Returns the local address of the socket `fd`.
+pub fn local_addr(fd: libc::c_int) -> io::Result<UnixSocketAddr> {
+    unsafe {
+        let mut addr: libc::sockaddr_un = mem::zeroed();
+        let mut len = mem::size_of_val(&addr) as libc::socklen_t;
+        cvt(libc::getsockname(fd, &mut addr as *mut _ as *mut _, &mut len))?;
+        Ok(UnixSocketAddr::from_raw(&addr, len))
+    }
+}
+
+/// Binds the socket `fd` to a unique address in the abstract namespace
+/// picked by the kernel, returning that address.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn autobind(fd: libc::c_int) -> io::Result<UnixSocketAddr> {
+    unsafe {
+        let (addr, len, _) = UnixSocketAddr::unnamed().to_raw()?;
+        cvt(libc::bind(fd, &addr as *const _ as *const _, len))
+            .map_err(|e| error::syscall("bind", None, e))?;
+    }
+    local_addr(fd)
+}
+
+/// Returns the address of the peer of the socket `fd`.
+pub fn peer_addr(fd: libc::c_int) -> io::Result<UnixSocketAddr> {
+    unsafe {
+        let mut addr: libc::sockaddr_un = mem::zeroed();
+        let mut len = mem::size_of_val(&addr) as libc::socklen_t;
+        cvt(libc::getpeername(fd, &mut addr as *mut _ as *mut _, &mut len))?;
+        Ok(UnixSocketAddr::from_raw(&addr, len))
+    }
+}
+
+impl<'a> From<&'a net::SocketAddr> for UnixSocketAddr {
+    fn from(addr: &'a net::SocketAddr) -> UnixSocketAddr {
+        let kind = if let Some(path) = addr.as_pathname() {
+            Kind::Pathname(path.to_path_buf())
+        } else if let Some(name) = abstract_name(addr) {
+            Kind::Abstract(name.to_vec())
+        } else {
+            Kind::Unnamed
+        };
+        UnixSocketAddr { kind }
+    }
+}
+
+impl From<net::SocketAddr> for UnixSocketAddr {
+    fn from(addr: net::SocketAddr) -> UnixSocketAddr {
+        UnixSocketAddr::from(&addr)
+    }
+}
+
+impl fmt::Display for UnixSocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Kind::Unnamed => f.write_str("(unnamed)"),
+            Kind::Pathname(ref path) => write!(f, "{}", path.display()),
+            Kind::Abstract(ref name) => {
+                f.write_str("@")?;
+                write_escaped(f, name)
+            }
+        }
+    }
+}
+
+/// Writes `name` with backslashes, control characters and invalid UTF-8
+/// escaped, as `parse_abstract` expects.
+fn write_escaped(f: &mut fmt::Formatter, mut name: &[u8]) -> fmt::Result {
+    loop {
+        let (valid, rest) = match str::from_utf8(name) {
+            Ok(s) => (s, &[][..]),
+            Err(e) => {
+                let (valid, rest) = name.split_at(e.valid_up_to());
+                (str::from_utf8(valid).unwrap(), rest)
+            }
+        };
+        for c in valid.chars() {
+            if c == '\\' {
+                f.write_str("\\\\")?;
+            } else if c.is_control() {
+                let mut buf = [0; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    write!(f, "\\x{:02x}", b)?;
+                }
+            } else {
+                write!(f, "{}", c)?;
+            }
+        }
+        // Escape one byte of invalid UTF-8 at a time, as the bytes after it
+        // may start a valid character.
+        match rest.split_first() {
+            Some((b, rest)) => {
+                write!(f, "\\x{:02x}", b)?;
+                name = rest;
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for UnixSocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            Kind::Unnamed => f.write_str("(unnamed)"),
+            Kind::Pathname(ref path) => write!(f, "{:?} (pathname)", path),
+            Kind::Abstract(ref name) => {
+                f.write_str("\"@")?;
+                for &b in name {
+                    for c in ascii::escape_default(b) {
+                        write!(f, "{}", c as char)?;
+                    }
+                }
+                f.write_str("\" (abstract)")
+            }
+        }
+    }
+}
+
+impl FromStr for UnixSocketAddr {
+    type Err = io::Error;
+
+    /// Parses a pathname address, or an abstract address if the string starts
+    /// with `@`. Relative pathnames starting with `@` must be prefixed with
+    /// `./` to be parsed as pathnames.
+    ///
+    /// Unnamed addresses can't be parsed, as no socket can be reached through
+    /// one.
+    fn from_str(s: &str) -> io::Result<UnixSocketAddr> {
+        match s.strip_prefix('@') {
+            Some(name) => parse_abstract(name),
+            None => UnixSocketAddr::from_pathname(s),
+        }
+    }
+}
+
+/// Parses an abstract name as displayed, turning `\xNN` and `\\` escapes
+/// back into the bytes they stand for.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_abstract(name: &str) -> io::Result<UnixSocketAddr> {
+    let invalid = || {
+        io::Error::new(io::ErrorKind::InvalidInput,
+                       "abstract addresses may only contain `\\xNN` and `\\\\` escapes")
+    };
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        if b != b'\\' {
+            bytes.push(b);
+            continue
+        }
+        match rest.split_first() {
+            Some((&b'\\', tail)) => {
+                bytes.push(b'\\');
+                rest = tail;
+            }
+            Some((&b'x', tail)) if tail.len() >= 2 &&
+                                      tail[..2].iter().all(u8::is_ascii_hexdigit) => {
+                let hex = str::from_utf8(&tail[..2]).unwrap();
+                bytes.push(u8::from_str_radix(hex, 16).unwrap());
+                rest = &tail[2..];
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    UnixSocketAddr::from_abstract(&bytes)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn parse_abstract(_name: &str) -> io::Result<UnixSocketAddr> {
+    Err(io::Error::new(io::ErrorKind::InvalidInput,
+                       "abstract addresses are not supported on this platform"))
+}
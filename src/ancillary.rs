@@ -0,0 +1,351 @@
+//! Ancillary data, otherwise known as control messages, which can be sent and
+//! received alongside regular data on Unix sockets.
+//!
+//! A `SocketAncillary` is a buffer of control messages which is filled in
+//! with the `add_*` methods before being passed to one of the
+//! `send_with_ancillary` family of methods, or which is filled in by the
+//! kernel when passed to one of the `recv_with_ancillary` family of methods.
+//! The received messages can then be inspected through `messages`.
+
+use std::mem;
+use std::os::unix::prelude::*;
+use std::ptr;
+use std::slice;
+
+use libc::{self, c_int, c_uint};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use UCred;
+
+// Not exposed by libc, but the value has been stable since it was introduced.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SCM_SECURITY: c_int = 0x03;
+
+/// A buffer of control messages for a Unix socket.
+///
+/// The buffer is allocated with a fixed capacity up front, which can be
+/// computed from the messages it's expected to hold with `space_for_fds` and
+/// friends. Storage is always suitably aligned for the `cmsghdr` structures
+/// within.
+///
+/// File descriptors received into the buffer are owned by the caller, and
+/// are not closed when the buffer is dropped or cleared, or when another
+/// message is received into it. They must be taken out through `messages`
+/// and closed, or they are leaked.
+#[derive(Debug)]
+pub struct SocketAncillary {
+    buffer: Vec<u64>,
+    capacity: usize,
+    length: usize,
+    truncated: bool,
+}
+
+impl SocketAncillary {
+    /// Creates an empty buffer which can hold `capacity` bytes of control
+    /// messages.
+    pub fn with_capacity(capacity: usize) -> SocketAncillary {
+        SocketAncillary {
+            buffer: vec![0; capacity / mem::size_of::<u64>() + 1],
+            capacity,
+            length: 0,
+            truncated: false,
+        }
+    }
+
+    /// Returns the number of bytes needed to hold one `SCM_RIGHTS` message
+    /// carrying `fds` file descriptors.
+    pub fn space_for_fds(fds: usize) -> usize {
+        space(fds * mem::size_of::<c_int>())
+    }
+
+    /// Returns the number of bytes needed to hold `creds` `SCM_CREDENTIALS`
+    /// messages.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn space_for_creds(creds: usize) -> usize {
+        creds * space(mem::size_of::<libc::ucred>())
+    }
+
+    /// Returns the number of bytes this buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of bytes of control messages in this buffer.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Returns whether there are no control messages in this buffer.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Returns whether the control messages last received into this buffer
+    /// were truncated (`MSG_CTRUNC`) because it wasn't large enough.
+    ///
+    /// Any file descriptors which didn't fit have been closed by the kernel.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Removes all control messages from this buffer.
+    ///
+    /// Note that any file descriptors received into this buffer which haven't
+    /// been taken care of will be leaked.
+    pub fn clear(&mut self) {
+        self.length = 0;
+        self.truncated = false;
+    }
+
+    /// Adds an `SCM_RIGHTS` message carrying the file descriptors in `fds`.
+    ///
+    /// The file descriptors remain owned by the caller, and the peer receives
+    /// duplicates of them. Returns `false` if there isn't enough room left in
+    /// the buffer, in which case it is left unchanged.
+    pub fn add_fds(&mut self, fds: &[RawFd]) -> bool {
+        let data = unsafe {
+            slice::from_raw_parts(fds.as_ptr() as *const u8, mem::size_of_val(fds))
+        };
+        self.add_message(libc::SOL_SOCKET, libc::SCM_RIGHTS, data)
+    }
+
+    /// Adds an `SCM_CREDENTIALS` message for each of `creds`.
+    ///
+    /// Unless the sender is privileged the credentials must match its own
+    /// process, user and group IDs. Returns `false` if there isn't enough
+    /// room left in the buffer, in which case it is left unchanged.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn add_creds(&mut self, creds: &[UCred]) -> bool {
+        if self.length + SocketAncillary::space_for_creds(creds.len()) > self.capacity {
+            return false
+        }
+        for cred in creds {
+            let cred = libc::ucred::from(*cred);
+            let data = unsafe {
+                slice::from_raw_parts(&cred as *const _ as *const u8,
+                                      mem::size_of_val(&cred))
+            };
+            self.add_message(libc::SOL_SOCKET, libc::SCM_CREDENTIALS, data);
+        }
+        true
+    }
+
+    /// Returns an iterator over the control messages in this buffer.
+    pub fn messages(&self) -> Messages<'_> {
+        Messages {
+            buffer: &self.bytes()[..self.length],
+            offset: 0,
+        }
+    }
+
+    fn add_message(&mut self, level: c_int, ty: c_int, data: &[u8]) -> bool {
+        let space = space(data.len());
+        if self.length + space > self.capacity {
+            return false
+        }
+        unsafe {
+            let start = (self.buffer.as_mut_ptr() as *mut u8).add(self.length);
+            ptr::write_bytes(start, 0, space);
+            let cmsg = start as *mut libc::cmsghdr;
+            (*cmsg).cmsg_level = level;
+            (*cmsg).cmsg_type = ty;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(data.len() as c_uint) as _;
+            ptr::copy_nonoverlapping(data.as_ptr(), libc::CMSG_DATA(cmsg), data.len());
+        }
+        self.length += space;
+        true
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts(self.buffer.as_ptr() as *const u8, self.capacity)
+        }
+    }
+
+    /// Returns the buffer and length to use for a `msghdr` when sending.
+    pub(crate) fn as_control(&self) -> (*mut libc::c_void, usize) {
+        if self.length == 0 {
+            (ptr::null_mut(), 0)
+        } else {
+            (self.buffer.as_ptr() as *mut _, self.length)
+        }
+    }
+
+    /// Returns the buffer and length to use for a `msghdr` when receiving.
+    pub(crate) fn as_control_mut(&mut self) -> (*mut libc::c_void, usize) {
+        self.clear();
+        if self.capacity == 0 {
+            (ptr::null_mut(), 0)
+        } else {
+            (self.buffer.as_mut_ptr() as *mut _, self.capacity)
+        }
+    }
+
+    /// Records the outcome of receiving control messages into this buffer.
+    pub(crate) fn set_received(&mut self, length: usize, truncated: bool) {
+        self.length = length;
+        self.truncated = truncated;
+    }
+
+    /// Takes ownership of up to `max_fds` received file descriptors, pushing
+    /// them onto `fds` and closing any beyond that.
+    ///
+    /// Returns whether any file descriptors were lost, either to truncation
+    /// or because there were more than `max_fds` of them.
+    pub(crate) fn take_fds(&mut self, fds: &mut Vec<OwnedFd>, max_fds: usize) -> bool {
+        let mut truncated = self.truncated;
+        let mut taken = 0;
+        for message in self.messages() {
+            if let AncillaryData::ScmRights(rights) = message {
+                for fd in rights {
+                    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+                    if taken < max_fds {
+                        fds.push(fd);
+                        taken += 1;
+                    } else {
+                        truncated = true;
+                    }
+                }
+            }
+        }
+        self.clear();
+        truncated
+    }
+}
+
+fn space(len: usize) -> usize {
+    unsafe { libc::CMSG_SPACE(len as c_uint) as usize }
+}
+
+/// An iterator over the control messages in a `SocketAncillary`.
+#[derive(Debug)]
+pub struct Messages<'a> {
+    buffer: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = AncillaryData<'a>;
+
+    fn next(&mut self) -> Option<AncillaryData<'a>> {
+        let header = unsafe { libc::CMSG_LEN(0) as usize };
+        if self.offset + header > self.buffer.len() {
+            return None
+        }
+        let (level, ty, len) = unsafe {
+            let cmsg = self.buffer.as_ptr().add(self.offset) as *const libc::cmsghdr;
+            let cmsg = ptr::read_unaligned(cmsg);
+            (cmsg.cmsg_level, cmsg.cmsg_type, cmsg.cmsg_len as usize)
+        };
+        if len < header || self.offset + len > self.buffer.len() {
+            return None
+        }
+        let data = &self.buffer[self.offset + header..self.offset + len];
+        self.offset += space(len - header);
+        Some(AncillaryData::new(level, ty, data))
+    }
+}
+
+/// A single control message.
+#[derive(Debug)]
+pub enum AncillaryData<'a> {
+    /// File descriptors passed with `SCM_RIGHTS`.
+    ScmRights(ScmRights<'a>),
+    /// Process credentials passed with `SCM_CREDENTIALS`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    ScmCredentials(ScmCredentials<'a>),
+    /// The security label of the sender passed with `SCM_SECURITY`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    ScmSecurity(ScmSecurity<'a>),
+    /// A control message this crate doesn't know how to decode.
+    Unknown {
+        /// The `cmsg_level` of the message.
+        level: c_int,
+        /// The `cmsg_type` of the message.
+        ty: c_int,
+        /// The payload of the message.
+        data: &'a [u8],
+    },
+}
+
+impl<'a> AncillaryData<'a> {
+    fn new(level: c_int, ty: c_int, data: &'a [u8]) -> AncillaryData<'a> {
+        match (level, ty) {
+            (libc::SOL_SOCKET, libc::SCM_RIGHTS) => {
+                AncillaryData::ScmRights(ScmRights { data })
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            (libc::SOL_SOCKET, libc::SCM_CREDENTIALS) => {
+                AncillaryData::ScmCredentials(ScmCredentials { data })
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            (libc::SOL_SOCKET, SCM_SECURITY) => {
+                AncillaryData::ScmSecurity(ScmSecurity { data })
+            }
+            _ => AncillaryData::Unknown { level, ty, data },
+        }
+    }
+}
+
+/// An iterator over the file descriptors in an `SCM_RIGHTS` message.
+///
+/// File descriptors received from a peer are owned by the receiver, which is
+/// responsible for closing them. They have the close-on-exec flag set.
+#[derive(Debug)]
+pub struct ScmRights<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for ScmRights<'a> {
+    type Item = RawFd;
+
+    fn next(&mut self) -> Option<RawFd> {
+        read_item(&mut self.data)
+    }
+}
+
+/// An iterator over the credentials in an `SCM_CREDENTIALS` message.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug)]
+pub struct ScmCredentials<'a> {
+    data: &'a [u8],
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl<'a> Iterator for ScmCredentials<'a> {
+    type Item = UCred;
+
+    fn next(&mut self) -> Option<UCred> {
+        read_item::<libc::ucred>(&mut self.data).map(UCred::from)
+    }
+}
+
+/// The security label in an `SCM_SECURITY` message.
+///
+/// These are only received on sockets with the `SO_PASSSEC` option enabled.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug)]
+pub struct ScmSecurity<'a> {
+    data: &'a [u8],
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl<'a> ScmSecurity<'a> {
+    /// Returns the security label, without any trailing nul byte.
+    pub fn label(&self) -> &'a [u8] {
+        match self.data.split_last() {
+            Some((&0, label)) => label,
+            _ => self.data,
+        }
+    }
+}
+
+fn read_item<T>(data: &mut &[u8]) -> Option<T> {
+    let size = mem::size_of::<T>();
+    if data.len() < size {
+        return None
+    }
+    let item = unsafe { ptr::read_unaligned(data.as_ptr() as *const T) };
+    *data = &data[size..];
+    Some(item)
+}
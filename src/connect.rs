@@ -0,0 +1,337 @@
+use std::cmp;
+use std::io;
+#[cfg(feature = "mio-06")]
+use std::mem;
+#[cfg(feature = "mio-06")]
+use std::os::unix::prelude::*;
+#[cfg(feature = "mio-06")]
+use std::path::Path;
+#[cfg(feature = "mio-06")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "mio-06")]
+use std::sync::{mpsc, Arc, Mutex};
+#[cfg(feature = "mio-06")]
+use std::thread;
+use std::time::{Duration, Instant};
+
+use libc;
+#[cfg(feature = "mio-06")]
+use mio::event::Evented;
+#[cfg(feature = "mio-06")]
+use mio::{Poll, PollOpt, Ready, Registration, SetReadiness, Token};
+
+#[cfg(feature = "mio-06")]
+use UnixStream;
+#[cfg(feature = "mio-06")]
+use addr::UnixSocketAddr;
+use cvt;
+use error;
+use socket::{self, Socket};
+
+/// A connection to a `UnixListener` which is waiting for room in the
+/// listener's backlog.
+///
+/// Connecting to a listener whose backlog is full fails straight away
+/// instead of the connection being left in progress. A `PendingConnect`
+/// retries in that case, and becomes writable once the connection has been
+/// made or has failed, at which point `connected` returns the outcome.
+///
+/// There is no way to be notified of room in the backlog through an event
+/// loop, so a single background thread shared by all `PendingConnect`s
+/// retries them, backing off while the backlogs stay full. The thread exits
+/// once no connections are left waiting, and a `PendingConnect` which is
+/// dropped is given up on.
+///
+/// This requires the `mio-06` feature, which is enabled by default. With
+/// mio 0.8 or tokio, `UnixStream::connect_timeout` can be called on a
+/// thread which is allowed to block, such as through tokio's
+/// `spawn_blocking`, instead.
+#[cfg(feature = "mio-06")]
+#[derive(Debug)]
+pub struct PendingConnect {
+    registration: Registration,
+    shared: Arc<Shared>,
+}
+
+#[cfg(feature = "mio-06")]
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    cancelled: AtomicBool,
+}
+
+#[cfg(feature = "mio-06")]
+#[derive(Debug)]
+enum State {
+    Pending,
+    Done(io::Result<UnixStream>),
+    Taken,
+}
+
+/// A connection waiting for room in a listener's backlog.
+#[cfg(feature = "mio-06")]
+struct Retry {
+    socket: Socket,
+    addr: libc::sockaddr_un,
+    len: libc::socklen_t,
+    // Keeps open the directory which a long path's address goes through.
+    _dir: Option<OwnedFd>,
+    shared: Arc<Shared>,
+    readiness: SetReadiness,
+}
+
+/// Sends connections to the thread retrying them, if it's running.
+#[cfg(feature = "mio-06")]
+static RETRIER: Mutex<Option<mpsc::Sender<Retry>>> = Mutex::new(None);
+
+#[cfg(feature = "mio-06")]
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(1);
+#[cfg(feature = "mio-06")]
+const MAX_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+#[cfg(feature = "mio-06")]
+impl PendingConnect {
+    /// Starts connecting to the socket named by `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<PendingConnect> {
+        let (addr, len, dir) = socket::sockaddr_un_holding_dir(path.as_ref())?;
+        PendingConnect::new_raw(&addr, len, dir)
+    }
+
+    /// Starts connecting to the socket at the specified address.
+    pub fn new_addr(addr: &UnixSocketAddr) -> io::Result<PendingConnect> {
+        let (addr, len, dir) = addr.to_raw()?;
+        PendingConnect::new_raw(&addr, len, dir)
+    }
+
+    fn new_raw(addr: &libc::sockaddr_un,
+               len: libc::socklen_t,
+               dir: Option<OwnedFd>) -> io::Result<PendingConnect> {
+        let (registration, readiness) = Registration::new2();
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::Pending),
+            cancelled: AtomicBool::new(false),
+        });
+
+        let socket = Socket::new(libc::SOCK_STREAM)?;
+        match socket::connect(socket.fd(), addr, len) {
+            Ok(()) => shared.finish(Ok(socket), &readiness),
+            Err(ref e) if socket::is_backlog_full(e) => {
+                retry(Retry {
+                    socket,
+                    addr: *addr,
+                    len,
+                    _dir: dir,
+                    shared: shared.clone(),
+                    readiness,
+                })?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(PendingConnect { registration, shared })
+    }
+
+    /// Returns the connected stream once the connection has been made.
+    ///
+    /// Returns `Ok(None)` while still waiting for room in the listener's
+    /// backlog, and the error if connecting failed. Once the stream or the
+    /// error has been returned, further calls return an error.
+    pub fn connected(&self) -> io::Result<Option<UnixStream>> {
+        let mut state = self.shared.state.lock().unwrap();
+        match mem::replace(&mut *state, State::Taken) {
+            State::Pending => {
+                *state = State::Pending;
+                Ok(None)
+            }
+            State::Done(res) => res.map(Some),
+            State::Taken => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "connection has already been taken"))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "mio-06")]
+impl Shared {
+    fn finish(&self, res: io::Result<Socket>, readiness: &SetReadiness) {
+        let res = res.map(|socket| unsafe { UnixStream::from_raw_fd(socket.into_fd()) });
+        *self.state.lock().unwrap() = State::Done(res);
+        let _ = readiness.set_readiness(Ready::writable());
+    }
+}
+
+#[cfg(feature = "mio-06")]
+impl Drop for PendingConnect {
+    fn drop(&mut self) {
+        self.shared.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "mio-06")]
+impl Retry {
+    /// Tries connecting again, returning the connection if it's still
+    /// waiting for room in the backlog.
+    fn attempt(self) -> Option<Retry> {
+        if self.shared.cancelled.load(Ordering::SeqCst) {
+            return None
+        }
+        match socket::connect(self.socket.fd(), &self.addr, self.len) {
+            Err(ref e) if socket::is_backlog_full(e) => Some(self),
+            res => {
+                let Retry { socket, shared, readiness, .. } = self;
+                shared.finish(res.map(|()| socket), &readiness);
+                None
+            }
+        }
+    }
+}
+
+/// Hands `retry` over to the thread retrying connections, starting it if
+/// it isn't running.
+#[cfg(feature = "mio-06")]
+fn retry(retry: Retry) -> io::Result<()> {
+    let mut sender = RETRIER.lock().unwrap();
+    let retry = match *sender {
+        Some(ref tx) => match tx.send(retry) {
+            Ok(()) => return Ok(()),
+            Err(mpsc::SendError(retry)) => retry,
+        },
+        None => retry,
+    };
+    let (tx, rx) = mpsc::channel();
+    let _ = tx.send(retry);
+    thread::Builder::new()
+        .name("mio-uds-connect".to_string())
+        .spawn(move || retry_connects(rx))?;
+    *sender = Some(tx);
+    Ok(())
+}
+
+/// Retries the connections received on `rx` until they've all been made or
+/// given up on, doubling the delay between attempts while none of them
+/// succeed.
+#[cfg(feature = "mio-06")]
+fn retry_connects(rx: mpsc::Receiver<Retry>) {
+    let mut pending = Vec::new();
+    let mut delay = MIN_RETRY_DELAY;
+    loop {
+        if pending.is_empty() {
+            // Nothing can be sent while the lock is held, so no connection
+            // is lost by exiting here.
+            let mut sender = RETRIER.lock().unwrap();
+            match rx.try_recv() {
+                Ok(retry) => pending.push(retry),
+                Err(_) => {
+                    *sender = None;
+                    return
+                }
+            }
+        } else {
+            match rx.recv_timeout(delay) {
+                Ok(retry) => {
+                    pending.push(retry);
+                    delay = MIN_RETRY_DELAY;
+                }
+                Err(_) => delay = cmp::min(delay * 2, MAX_RETRY_DELAY),
+            }
+        }
+        let before = pending.len();
+        pending = pending.into_iter().filter_map(Retry::attempt).collect();
+        if pending.len() < before {
+            delay = MIN_RETRY_DELAY;
+        }
+    }
+}
+
+/// Connects a new stream socket to `addr`, waiting for up to `timeout` for
+/// room in the listener's backlog or for the connection to complete.
+pub fn connect_timeout(addr: &libc::sockaddr_un,
+                       len: libc::socklen_t,
+                       timeout: Duration) -> io::Result<Socket> {
+    if timeout == Duration::from_secs(0) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "cannot set a 0 duration timeout"))
+    }
+    // A timeout too long to represent is as good as none.
+    let deadline = Instant::now().checked_add(timeout);
+    let socket = Socket::new(libc::SOCK_STREAM)?;
+    match socket::connect(socket.fd(), addr, len) {
+        Ok(()) => {
+            // The connection may still be in progress on some platforms.
+            socket::wait(socket.fd(), libc::POLLOUT, Some(timeout))?;
+            if let Some(e) = socket.take_error()? {
+                return Err(error::syscall("connect", socket::err_addr(addr, len), e))
+            }
+        }
+        Err(ref e) if socket::is_backlog_full(e) => {
+            connect_blocking(&socket, addr, len, deadline)?;
+        }
+        Err(e) => return Err(e),
+    }
+    Ok(socket)
+}
+
+/// Connects `socket` to `addr`, waiting for room in the listener's backlog
+/// until it's done or `deadline`, if any, passes.
+fn connect_blocking(socket: &Socket,
+                    addr: &libc::sockaddr_un,
+                    len: libc::socklen_t,
+                    deadline: Option<Instant>) -> io::Result<()> {
+    set_nonblocking(socket.fd(), false)?;
+    let res = loop {
+        // A blocking connect waits for room in the backlog for up to the
+        // send timeout, which is kept short enough to fit in `tv_usec`.
+        let mut wait = Duration::from_millis(100);
+        if let Some(deadline) = deadline {
+            let now = Instant::now();
+            if now >= deadline {
+                break Err(io::Error::new(io::ErrorKind::TimedOut, "connection timed out"))
+            }
+            wait = cmp::min(wait, deadline - now);
+        }
+        let timeout = libc::timeval {
+            tv_sec: 0,
+            tv_usec: cmp::max(wait.subsec_micros(), 1) as libc::suseconds_t,
+        };
+        socket::setsockopt(socket.fd(), libc::SOL_SOCKET, libc::SO_SNDTIMEO, timeout)?;
+        match cvt(unsafe { libc::connect(socket.fd(), addr as *const _ as *const _, len) }) {
+            Ok(_) => break Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => break Err(error::syscall("connect", socket::err_addr(addr, len), e)),
+        }
+    };
+    let timeout = libc::timeval { tv_sec: 0, tv_usec: 0 };
+    socket::setsockopt(socket.fd(), libc::SOL_SOCKET, libc::SO_SNDTIMEO, timeout)?;
+    set_nonblocking(socket.fd(), true)?;
+    res
+}
+
+fn set_nonblocking(fd: libc::c_int, nonblocking: bool) -> io::Result<()> {
+    let mut nonblocking = nonblocking as libc::c_ulong;
+    unsafe {
+        cvt(libc::ioctl(fd, libc::FIONBIO, &mut nonblocking))?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "mio-06")]
+impl Evented for PendingConnect {
+    fn register(&self, poll: &Poll, token: Token, events: Ready, opts: PollOpt) -> io::Result<()> {
+        Evented::register(&self.registration, poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        Evented::reregister(&self.registration, poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        Evented::deregister(&self.registration, poll)
+    }
+}
@@ -1,16 +1,39 @@
-use std::io;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::cmp;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::mem;
 use std::net::Shutdown;
 use std::os::unix::net;
 use std::os::unix::prelude::*;
 use std::path::Path;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::ptr;
+use std::time::Duration;
 
+#[cfg(feature = "iovec")]
+use iovec::IoVec;
+#[cfg(feature = "iovec")]
+use iovec::unix as iovec;
 use libc;
+#[cfg(feature = "mio-06")]
 use mio::event::Evented;
+#[cfg(feature = "mio-06")]
 use mio::unix::EventedFd;
+#[cfg(feature = "mio-06")]
 use mio::{Poll, Token, Ready, PollOpt};
+#[cfg(feature = "mio-08")]
+use mio08::{self, Interest, Registry};
+#[cfg(feature = "mio-08")]
+use mio08::unix::SourceFd;
 
+use addr::{self, UnixSocketAddr};
+use ancillary::SocketAncillary;
 use cvt;
-use socket::{sockaddr_un, Socket};
+use error;
+use perms::{self, Existing, FilePerms};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use ucred::{self, UCred};
+use socket::{self, BufferSizes, Socket};
 
 /// A Unix datagram socket.
 #[derive(Debug)]
@@ -25,15 +48,44 @@ impl UnixDatagram {
     }
 
     fn _bind(path: &Path) -> io::Result<UnixDatagram> {
-        unsafe {
-            let (addr, len) = try!(sockaddr_un(path));
-            let fd = try!(Socket::new(libc::SOCK_DGRAM));
+        socket::with_sockaddr_un(path, UnixDatagram::bind_raw)
+    }
 
-            let addr = &addr as *const _ as *const _;
-            try!(cvt(libc::bind(fd.fd(), addr, len)));
+    /// Creates a Unix datagram socket bound to the socket named `name` in the
+    /// directory `dir`.
+    ///
+    /// This makes it possible to bind to paths which are too long to fit in
+    /// a socket address, which `bind` also falls back to automatically. The
+    /// address is formed through `/proc/self/fd`, which is also what
+    /// `local_addr` will report, so `SocketFileGuard::for_socket` can't be
+    /// used with the socket.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn bind_at<D: AsRawFd, P: AsRef<Path>>(dir: &D, name: P) -> io::Result<UnixDatagram> {
+        socket::with_sockaddr_un_at(dir.as_raw_fd(), name.as_ref(), UnixDatagram::bind_raw)
+    }
 
-            Ok(UnixDatagram::from_raw_fd(fd.into_fd()))
-        }
+    /// Creates a Unix datagram socket bound to the given path, first removing
+    /// the socket file left behind by a previous socket at that path if it
+    /// has been closed.
+    ///
+    /// If another socket is still bound to the path, or the path exists but
+    /// isn't a socket, an error of kind `AddrInUse` is returned and the file
+    /// is left alone.
+    pub fn bind_reclaiming<P: AsRef<Path>>(path: P) -> io::Result<UnixDatagram> {
+        UnixDatagramBuilder::new().reclaim_stale(true).bind(path)
+    }
+
+    /// Creates a Unix datagram socket bound to the given address.
+    ///
+    /// This is the same as `bind`, except that abstract addresses can be
+    /// bound to directly rather than through paths starting with a nul byte.
+    pub fn bind_addr(addr: &UnixSocketAddr) -> io::Result<UnixDatagram> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        UnixDatagram::bind_raw(&addr, len)
+    }
+
+    fn bind_raw(addr: &libc::sockaddr_un, len: libc::socklen_t) -> io::Result<UnixDatagram> {
+        UnixDatagramBuilder::new().bind_raw(addr, len)
     }
 
     /// Consumes a standard library `UnixDatagram` and returns a wrapped
@@ -64,12 +116,54 @@ impl UnixDatagram {
         Ok(UnixDatagram { inner: stream })
     }
 
+    /// Creates a Unix datagram socket bound to an address in the abstract
+    /// namespace which is picked by the kernel.
+    ///
+    /// Unlike sockets created with `unbound`, peers can reply to datagrams
+    /// sent from this socket, and no file needs to be cleaned up afterwards.
+    /// The assigned address is returned alongside the socket.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn autobind() -> io::Result<(UnixDatagram, UnixSocketAddr)> {
+        let socket = UnixDatagram::unbound()?;
+        let addr = addr::autobind(socket.as_raw_fd())?;
+        Ok((socket, addr))
+    }
+
     /// Connects the socket to the specified address.
     ///
     /// The `send` method may be used to send data to the specified address.
     /// `recv` and `recv_from` will only receive data from that address.
     pub fn connect<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        self.inner.connect(path)
+        socket::with_sockaddr_un(path.as_ref(), |addr, len| self.connect_raw(addr, len))
+    }
+
+    /// Connects the socket to the socket named `name` in the directory `dir`.
+    ///
+    /// This makes it possible to connect to paths which are too long to fit
+    /// in a socket address, which `connect` also falls back to
+    /// automatically.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn connect_at<D: AsRawFd, P: AsRef<Path>>(&self, dir: &D, name: P) -> io::Result<()> {
+        socket::with_sockaddr_un_at(dir.as_raw_fd(), name.as_ref(), |addr, len| {
+            self.connect_raw(addr, len)
+        })
+    }
+
+    /// Connects the socket to the specified address.
+    ///
+    /// This is the same as `connect`, except that it takes a `UnixSocketAddr`
+    /// such as one returned by `recv_from_unix_addr`.
+    pub fn connect_addr(&self, addr: &UnixSocketAddr) -> io::Result<()> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        self.connect_raw(&addr, len)
+    }
+
+    fn connect_raw(&self, addr: &libc::sockaddr_un, len: libc::socklen_t) -> io::Result<()> {
+        unsafe {
+            cvt(libc::connect(self.inner.as_raw_fd(), addr as *const _ as *const _, len))
+                .map_err(|e| error::syscall("connect", socket::err_addr(addr, len), e))?;
+            Ok(())
+        }
     }
 
     /// Creates a new independently owned handle to the underlying socket.
@@ -88,6 +182,11 @@ impl UnixDatagram {
         self.inner.local_addr()
     }
 
+    /// Returns the address of this socket as a `UnixSocketAddr`.
+    pub fn local_unix_addr(&self) -> io::Result<UnixSocketAddr> {
+        addr::local_addr(self.inner.as_raw_fd())
+    }
+
     /// Returns the address of this socket's peer.
     ///
     /// The `connect` method will connect the socket to a peer.
@@ -95,6 +194,13 @@ impl UnixDatagram {
         self.inner.peer_addr()
     }
 
+    /// Returns the address of this socket's peer as a `UnixSocketAddr`.
+    ///
+    /// The `connect` method will connect the socket to a peer.
+    pub fn peer_unix_addr(&self) -> io::Result<UnixSocketAddr> {
+        addr::peer_addr(self.inner.as_raw_fd())
+    }
+
     /// Receives data from the socket.
     ///
     /// On success, returns the number of bytes read and the address from
@@ -103,6 +209,29 @@ impl UnixDatagram {
         self.inner.recv_from(buf)
     }
 
+    /// Receives data from the socket.
+    ///
+    /// On success, returns the number of bytes read and the address from
+    /// whence the data came as a `UnixSocketAddr`, which unlike `recv_from`
+    /// can represent abstract and unnamed senders on all platforms.
+    pub fn recv_from_unix_addr(&self, buf: &mut [u8]) -> io::Result<(usize, UnixSocketAddr)> {
+        unsafe {
+            let mut addr: libc::sockaddr_un = mem::zeroed();
+            let mut len = mem::size_of_val(&addr) as libc::socklen_t;
+            let rc = libc::recvfrom(self.inner.as_raw_fd(),
+                                    buf.as_mut_ptr() as *mut _,
+                                    buf.len(),
+                                    0,
+                                    &mut addr as *mut _ as *mut _,
+                                    &mut len);
+            if rc < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok((rc as usize, UnixSocketAddr::from_raw(&addr, len)))
+            }
+        }
+    }
+
     /// Receives data from the socket.
     ///
     /// On success, returns the number of bytes read.
@@ -114,7 +243,38 @@ impl UnixDatagram {
     ///
     /// On success, returns the number of bytes written.
     pub fn send_to<P: AsRef<Path>>(&self, buf: &[u8], path: P) -> io::Result<usize> {
-        self.inner.send_to(buf, path)
+        socket::with_sockaddr_un(path.as_ref(), |addr, len| self.send_to_raw(buf, addr, len))
+    }
+
+    /// Sends data on the socket to the specified address.
+    ///
+    /// This is the same as `send_to`, except that it takes a `UnixSocketAddr`.
+    /// In particular this allows replying to the abstract address of a
+    /// sender as returned by `recv_from_unix_addr`.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_to_addr(&self, buf: &[u8], addr: &UnixSocketAddr) -> io::Result<usize> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        self.send_to_raw(buf, &addr, len)
+    }
+
+    fn send_to_raw(&self,
+                   buf: &[u8],
+                   addr: &libc::sockaddr_un,
+                   len: libc::socklen_t) -> io::Result<usize> {
+        unsafe {
+            let rc = libc::sendto(self.inner.as_raw_fd(),
+                                  buf.as_ptr() as *const _,
+                                  buf.len(),
+                                  socket::MSG_NOSIGNAL,
+                                  addr as *const _ as *const _,
+                                  len);
+            if rc < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(rc as usize)
+            }
+        }
     }
 
     /// Sends data on the socket to the socket's peer.
@@ -127,11 +287,548 @@ impl UnixDatagram {
         self.inner.send(buf)
     }
 
+    /// Sends data along with a list of file descriptors to the specified
+    /// address.
+    ///
+    /// The file descriptors in `fds` are passed as an `SCM_RIGHTS` control
+    /// message attached to the datagram, so they're delivered atomically with
+    /// it. The file descriptors remain owned by the caller, and the receiver
+    /// gets duplicates of them.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_to_with_fds<P: AsRef<Path>>(&self,
+                                            buf: &[u8],
+                                            fds: &[RawFd],
+                                            path: P) -> io::Result<usize> {
+        self.send_to_with_ancillary(buf, &fds_ancillary(fds), path)
+    }
+
+    /// Sends data along with a list of file descriptors to the socket's peer.
+    ///
+    /// The peer address may be set by the `connect` method, and this method
+    /// will return an error if the socket has not already been connected.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_with_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        self.send_with_ancillary(buf, &fds_ancillary(fds))
+    }
+
+    /// Receives a datagram along with any file descriptors attached to it.
+    ///
+    /// Up to `max_fds` file descriptors received with the datagram are pushed
+    /// onto `fds`. They are owned by the caller and have the close-on-exec
+    /// flag set.
+    ///
+    /// On success, returns the number of bytes read, whether the sender
+    /// attached more file descriptors than `max_fds`, and the address from
+    /// whence the data came. If there were too many file descriptors the
+    /// excess ones have been closed by the kernel and are lost.
+    pub fn recv_from_with_fds(&self,
+                              buf: &mut [u8],
+                              fds: &mut Vec<OwnedFd>,
+                              max_fds: usize)
+                              -> io::Result<(usize, bool, UnixSocketAddr)> {
+        let mut ancillary = SocketAncillary::with_capacity(SocketAncillary::space_for_fds(max_fds));
+        let (n, addr) = self.recv_from_with_ancillary(buf, &mut ancillary)?;
+        Ok((n, ancillary.take_fds(fds, max_fds), addr))
+    }
+
+    /// Sets the value of the `SO_PASSCRED` option for this socket.
+    ///
+    /// When enabled, the credentials of the sending process are attached to
+    /// every datagram received on this socket and can be retrieved with
+    /// `recv_from_with_cred`. Enabling this option on an unbound socket also
+    /// autobinds it to an abstract address.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_passcred(&self, passcred: bool) -> io::Result<()> {
+        ucred::set_passcred(self.inner.as_raw_fd(), passcred)
+    }
+
+    /// Returns the value of the `SO_PASSCRED` option for this socket.
+    ///
+    /// For more information about this option, see `set_passcred`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn passcred(&self) -> io::Result<bool> {
+        ucred::passcred(self.inner.as_raw_fd())
+    }
+
+    /// Sends data with explicit credentials attached to the specified
+    /// address.
+    ///
+    /// Unless the sending process is privileged, `cred` must match its own
+    /// process, user and group IDs. The receiver gets them if it has enabled
+    /// `SO_PASSCRED`.
+    ///
+    /// On success, returns the number of bytes written.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn send_to_with_cred<P: AsRef<Path>>(&self,
+                                             buf: &[u8],
+                                             cred: UCred,
+                                             path: P) -> io::Result<usize> {
+        self.send_to_with_ancillary(buf, &ucred::send_cred_ancillary(cred), path)
+    }
+
+    /// Sends data with explicit credentials attached to the socket's peer.
+    ///
+    /// The peer address may be set by the `connect` method, and this method
+    /// will return an error if the socket has not already been connected.
+    ///
+    /// On success, returns the number of bytes written.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn send_with_cred(&self, buf: &[u8], cred: UCred) -> io::Result<usize> {
+        self.send_with_ancillary(buf, &ucred::send_cred_ancillary(cred))
+    }
+
+    /// Receives a datagram along with the credentials of the process which
+    /// sent it.
+    ///
+    /// Credentials are only received if `SO_PASSCRED` has been enabled on
+    /// this socket with `set_passcred`, otherwise `None` is returned for them.
+    /// Any file descriptors attached to the datagram are closed.
+    ///
+    /// On success, returns the number of bytes read, the address from whence
+    /// the data came and the sender's credentials.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn recv_from_with_cred(&self, buf: &mut [u8])
+                               -> io::Result<(usize, UnixSocketAddr, Option<UCred>)> {
+        let mut ancillary = ucred::cred_ancillary();
+        let (n, addr) = self.recv_from_with_ancillary(buf, &mut ancillary)?;
+        Ok((n, addr, ucred::received_cred(&mut ancillary)))
+    }
+
+    /// Receives a datagram along with the credentials of the process which
+    /// sent it.
+    ///
+    /// Credentials are only received if `SO_PASSCRED` has been enabled on
+    /// this socket with `set_passcred`, otherwise `None` is returned for them.
+    /// Any file descriptors attached to the datagram are closed.
+    ///
+    /// On success, returns the number of bytes read and the sender's
+    /// credentials.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn recv_with_cred(&self, buf: &mut [u8]) -> io::Result<(usize, Option<UCred>)> {
+        let mut ancillary = ucred::cred_ancillary();
+        let n = self.recv_with_ancillary(buf, &mut ancillary)?;
+        Ok((n, ucred::received_cred(&mut ancillary)))
+    }
+
+    /// Sends data along with the control messages in `ancillary` to the
+    /// specified address.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_to_with_ancillary<P: AsRef<Path>>(&self,
+                                                  buf: &[u8],
+                                                  ancillary: &SocketAncillary,
+                                                  path: P) -> io::Result<usize> {
+        socket::with_sockaddr_un(path.as_ref(), |addr, len| {
+            socket::send_msg(self.inner.as_raw_fd(), buf, ancillary, Some((addr, len)))
+        })
+    }
+
+    /// Sends data along with the control messages in `ancillary` to the
+    /// socket's peer.
+    ///
+    /// The peer address may be set by the `connect` method, and this method
+    /// will return an error if the socket has not already been connected.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_with_ancillary(&self,
+                               buf: &[u8],
+                               ancillary: &SocketAncillary) -> io::Result<usize> {
+        socket::send_msg(self.inner.as_raw_fd(), buf, ancillary, None)
+    }
+
+    /// Receives a datagram along with any control messages attached to it.
+    ///
+    /// The control messages are written to `ancillary`, replacing its previous
+    /// contents. On success, returns the number of bytes read and the address
+    /// from whence the data came.
+    pub fn recv_from_with_ancillary(&self,
+                                    buf: &mut [u8],
+                                    ancillary: &mut SocketAncillary)
+                                    -> io::Result<(usize, UnixSocketAddr)> {
+        let mut addr = unsafe { mem::zeroed() };
+        let (n, len) = socket::recv_msg(self.inner.as_raw_fd(), buf, ancillary, &mut addr)?;
+        Ok((n, UnixSocketAddr::from_raw(&addr, len)))
+    }
+
+    /// Receives a datagram along with any control messages attached to it.
+    ///
+    /// The control messages are written to `ancillary`, replacing its previous
+    /// contents. On success, returns the number of bytes read.
+    pub fn recv_with_ancillary(&self,
+                               buf: &mut [u8],
+                               ancillary: &mut SocketAncillary) -> io::Result<usize> {
+        let mut addr = unsafe { mem::zeroed() };
+        socket::recv_msg(self.inner.as_raw_fd(), buf, ancillary, &mut addr).map(|p| p.0)
+    }
+
     /// Returns the value of the `SO_ERROR` option.
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         self.inner.take_error()
     }
 
+    /// Blocks until this socket is readable, or until `timeout` has passed
+    /// if it's not `None`.
+    ///
+    /// This is useful for simple programs which don't otherwise need an
+    /// event loop. An error of kind `TimedOut` is returned if the timeout
+    /// passes first.
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        socket::wait(self.as_raw_fd(), libc::POLLIN, timeout)
+    }
+
+    /// Blocks until this socket is writable, or until `timeout` has passed
+    /// if it's not `None`.
+    ///
+    /// An error of kind `TimedOut` is returned if the timeout passes first.
+    pub fn wait_writable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        socket::wait(self.as_raw_fd(), libc::POLLOUT, timeout)
+    }
+
+    /// Receives a datagram from the socket into a list of buffers.
+    ///
+    /// The buffers are filled in sequentially, a buffer being entirely
+    /// filled up before the next is written to. Any part of the datagram
+    /// which doesn't fit in the buffers is discarded.
+    ///
+    /// On success, returns the number of bytes read.
+    pub fn recv_vectored(&self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        self.recv_iov(socket::iovecs_mut(bufs)).map(|(n, _)| n)
+    }
+
+    /// Receives a datagram from the socket into a list of buffers, as
+    /// `recv_vectored` does.
+    ///
+    /// On success, returns the number of bytes read and the address from
+    /// whence the data came.
+    pub fn recv_from_vectored(&self, bufs: &mut [IoSliceMut])
+                              -> io::Result<(usize, UnixSocketAddr)> {
+        self.recv_iov(socket::iovecs_mut(bufs))
+    }
+
+    /// Sends the contents of a list of buffers on the socket to the
+    /// specified address, as a single datagram.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_to_vectored<P: AsRef<Path>>(&self, bufs: &[IoSlice], path: P) -> io::Result<usize> {
+        self.send_to_iov(socket::iovecs(bufs), path.as_ref())
+    }
+
+    /// Sends the contents of a list of buffers on the socket to the socket's
+    /// peer, as a single datagram.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_vectored(&self, bufs: &[IoSlice]) -> io::Result<usize> {
+        socket::send_iov(self.as_raw_fd(), socket::iovecs(bufs), None)
+    }
+
+    /// Receives a datagram from the socket into a list of `IoVec`s, as
+    /// `recv_vectored` does.
+    ///
+    /// This requires the `iovec` feature, which is enabled by default.
+    #[cfg(feature = "iovec")]
+    pub fn recv_bufs(&self, bufs: &mut [&mut IoVec]) -> io::Result<usize> {
+        self.recv_iov(iovec::as_os_slice_mut(bufs)).map(|(n, _)| n)
+    }
+
+    /// Receives a datagram from the socket into a list of `IoVec`s, as
+    /// `recv_from_vectored` does.
+    ///
+    /// This requires the `iovec` feature, which is enabled by default.
+    #[cfg(feature = "iovec")]
+    pub fn recv_from_bufs(&self, bufs: &mut [&mut IoVec])
+                          -> io::Result<(usize, UnixSocketAddr)> {
+        self.recv_iov(iovec::as_os_slice_mut(bufs))
+    }
+
+    /// Sends the contents of a list of `IoVec`s on the socket to the
+    /// specified address, as `send_to_vectored` does.
+    ///
+    /// This requires the `iovec` feature, which is enabled by default.
+    #[cfg(feature = "iovec")]
+    pub fn send_to_bufs<P: AsRef<Path>>(&self, bufs: &[&IoVec], path: P) -> io::Result<usize> {
+        self.send_to_iov(iovec::as_os_slice(bufs), path.as_ref())
+    }
+
+    /// Sends the contents of a list of `IoVec`s on the socket to the
+    /// socket's peer, as `send_vectored` does.
+    ///
+    /// This requires the `iovec` feature, which is enabled by default.
+    #[cfg(feature = "iovec")]
+    pub fn send_bufs(&self, bufs: &[&IoVec]) -> io::Result<usize> {
+        socket::send_iov(self.as_raw_fd(), iovec::as_os_slice(bufs), None)
+    }
+
+    fn recv_iov(&self, iov: &[libc::iovec]) -> io::Result<(usize, UnixSocketAddr)> {
+        let mut addr = unsafe { mem::zeroed() };
+        let (n, len) = socket::recv_iov(self.as_raw_fd(), iov, &mut addr)?;
+        Ok((n, UnixSocketAddr::from_raw(&addr, len)))
+    }
+
+    fn send_to_iov(&self, iov: &[libc::iovec], path: &Path) -> io::Result<usize> {
+        socket::with_sockaddr_un(path, |addr, len| {
+            socket::send_iov(self.as_raw_fd(), iov, Some((addr, len)))
+        })
+    }
+
+    /// Receives a datagram from the socket without removing it from the
+    /// queue.
+    ///
+    /// On success, returns the number of bytes read. Successive calls return
+    /// the same datagram, until it's consumed by `recv` or `recv_from`.
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_with_flags(buf, libc::MSG_PEEK).map(|(n, _)| n)
+    }
+
+    /// Receives a datagram from the socket without removing it from the
+    /// queue.
+    ///
+    /// On success, returns the number of bytes read and the address from
+    /// whence the data came.
+    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, UnixSocketAddr)> {
+        let mut addr = unsafe { mem::zeroed() };
+        let (n, _, len) = socket::recv_flags(self.as_raw_fd(), buf, libc::MSG_PEEK, &mut addr)?;
+        Ok((n, UnixSocketAddr::from_raw(&addr, len)))
+    }
+
+    /// Receives a datagram from the socket with the `MSG_*` flags in
+    /// `flags`, such as `libc::MSG_PEEK` or `libc::MSG_TRUNC`.
+    ///
+    /// On success, returns the number of bytes read and the flags describing
+    /// the received datagram (`msg_flags`). In particular `libc::MSG_TRUNC`
+    /// is set if the datagram was larger than `buf`, in which case the rest
+    /// of it was discarded. The number of bytes read never exceeds the length
+    /// of `buf`, even though passing `libc::MSG_TRUNC` makes the system
+    /// report the full length of the datagram; use `next_datagram_size` to
+    /// find that out instead.
+    pub fn recv_with_flags(&self, buf: &mut [u8], flags: libc::c_int)
+                           -> io::Result<(usize, libc::c_int)> {
+        let mut addr = unsafe { mem::zeroed() };
+        socket::recv_flags(self.as_raw_fd(), buf, flags, &mut addr).map(|(n, flags, _)| (n, flags))
+    }
+
+    /// Sends data on the socket to the socket's peer with the `MSG_*` flags
+    /// in `flags`, such as `libc::MSG_DONTWAIT`.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_with_flags(&self, buf: &[u8], flags: libc::c_int) -> io::Result<usize> {
+        socket::send_flags(self.as_raw_fd(), buf, flags)
+    }
+
+    /// Returns the size of the next datagram waiting to be received, without
+    /// removing it from the queue.
+    ///
+    /// This allows sizing the buffer passed to `recv` exactly. If no
+    /// datagram is available yet then a "would block" error is returned.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn next_datagram_size(&self) -> io::Result<usize> {
+        unsafe {
+            // With MSG_TRUNC, Linux returns the full length of the datagram
+            // rather than how much of it fit in the buffer.
+            let rc = libc::recv(self.as_raw_fd(),
+                                ptr::null_mut(),
+                                0,
+                                libc::MSG_PEEK | libc::MSG_TRUNC);
+            if rc < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(rc as usize)
+            }
+        }
+    }
+
+    /// Receives several datagrams from the socket with a single system call
+    /// (`recvmmsg`).
+    ///
+    /// Up to one datagram is received into each buffer in `bufs`, and the
+    /// corresponding entry of `meta` is filled in with its length, whether it
+    /// was truncated to fit the buffer, and the address of its sender. At
+    /// most `min(bufs.len(), meta.len())` datagrams are received.
+    ///
+    /// On success, returns the number of datagrams received, which is at
+    /// least one. If no datagram is available yet then a "would block" error
+    /// is returned.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn recv_many(&self, bufs: &mut [&mut [u8]], meta: &mut [RecvMeta]) -> io::Result<usize> {
+        let n = cmp::min(bufs.len(), meta.len());
+        if n == 0 {
+            return Ok(0)
+        }
+        unsafe {
+            let mut addrs: Vec<libc::sockaddr_un> = vec![mem::zeroed(); n];
+            let mut iovs = bufs[..n].iter_mut().map(|buf| {
+                libc::iovec {
+                    iov_base: buf.as_mut_ptr() as *mut _,
+                    iov_len: buf.len(),
+                }
+            }).collect::<Vec<_>>();
+            let mut msgs = iovs.iter_mut().zip(addrs.iter_mut()).map(|(iov, addr)| {
+                let mut msg: libc::mmsghdr = mem::zeroed();
+                msg.msg_hdr.msg_iov = iov;
+                msg.msg_hdr.msg_iovlen = 1;
+                msg.msg_hdr.msg_name = addr as *mut _ as *mut _;
+                msg.msg_hdr.msg_namelen = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+                msg
+            }).collect::<Vec<_>>();
+
+            let rc = libc::recvmmsg(self.as_raw_fd(),
+                                    msgs.as_mut_ptr(),
+                                    n as libc::c_uint,
+                                    0,
+                                    ptr::null_mut());
+            if rc < 0 {
+                return Err(io::Error::last_os_error())
+            }
+            let received = rc as usize;
+            for (i, msg) in msgs[..received].iter().enumerate() {
+                meta[i] = RecvMeta {
+                    len: msg.msg_len as usize,
+                    truncated: msg.msg_hdr.msg_flags & libc::MSG_TRUNC != 0,
+                    addr: UnixSocketAddr::from_raw(&addrs[i], msg.msg_hdr.msg_namelen),
+                };
+            }
+            Ok(received)
+        }
+    }
+
+    /// Sends several datagrams to the socket's peer with a single system
+    /// call (`sendmmsg`), one for each buffer in `bufs`.
+    ///
+    /// On success, returns the number of datagrams sent. This may be fewer
+    /// than `bufs.len()` if the send buffer fills up, in which case the rest
+    /// should be sent again once the socket is writable.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn send_many(&self, bufs: &[&[u8]]) -> io::Result<usize> {
+        self.send_mmsg(bufs, None)
+    }
+
+    /// Sends several datagrams with a single system call (`sendmmsg`), the
+    /// contents of each buffer in `bufs` to the address at the same position
+    /// in `addrs`.
+    ///
+    /// On success, returns the number of datagrams sent, as for `send_many`.
+    /// Returns an error if `bufs` and `addrs` differ in length.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn send_many_to(&self, bufs: &[&[u8]], addrs: &[UnixSocketAddr]) -> io::Result<usize> {
+        if bufs.len() != addrs.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "number of buffers and addresses differ"))
+        }
+        let raw = addrs.iter().map(|addr| addr.to_raw()).collect::<io::Result<Vec<_>>>()?;
+        let addrs = raw.iter().map(|&(addr, len, _)| (addr, len)).collect::<Vec<_>>();
+        self.send_mmsg(bufs, Some(&addrs))
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn send_mmsg(&self,
+                 bufs: &[&[u8]],
+                 addrs: Option<&[(libc::sockaddr_un, libc::socklen_t)]>)
+                 -> io::Result<usize> {
+        if bufs.is_empty() {
+            return Ok(0)
+        }
+        unsafe {
+            let mut iovs = bufs.iter().map(|buf| {
+                libc::iovec {
+                    iov_base: buf.as_ptr() as *mut _,
+                    iov_len: buf.len(),
+                }
+            }).collect::<Vec<_>>();
+            let mut msgs = iovs.iter_mut().enumerate().map(|(i, iov)| {
+                let mut msg: libc::mmsghdr = mem::zeroed();
+                msg.msg_hdr.msg_iov = iov;
+                msg.msg_hdr.msg_iovlen = 1;
+                if let Some(addrs) = addrs {
+                    let (ref addr, len) = addrs[i];
+                    msg.msg_hdr.msg_name = addr as *const _ as *mut _;
+                    msg.msg_hdr.msg_namelen = len;
+                }
+                msg
+            }).collect::<Vec<_>>();
+
+            let rc = libc::sendmmsg(self.as_raw_fd(),
+                                    msgs.as_mut_ptr(),
+                                    msgs.len() as libc::c_uint,
+                                    socket::MSG_NOSIGNAL as _);
+            if rc < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(rc as usize)
+            }
+        }
+    }
+
+    /// Sets the size of the send buffer (`SO_SNDBUF`).
+    ///
+    /// The kernel caps this, on Linux to `net.core.wmem_max`, and may double
+    /// the value to leave room for bookkeeping.
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_SNDBUF, size)
+    }
+
+    /// Returns the size of the send buffer (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_SNDBUF)
+    }
+
+    /// Sets the size of the receive buffer (`SO_RCVBUF`).
+    ///
+    /// The kernel caps this, on Linux to `net.core.rmem_max`, and may double
+    /// the value to leave room for bookkeeping.
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_RCVBUF, size)
+    }
+
+    /// Returns the size of the receive buffer (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_RCVBUF)
+    }
+
+    /// Sets the size of the send buffer ignoring the system-wide limit
+    /// (`SO_SNDBUFFORCE`).
+    ///
+    /// This requires the `CAP_NET_ADMIN` capability.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_send_buffer_size_force(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_SNDBUFFORCE, size)
+    }
+
+    /// Sets the size of the receive buffer ignoring the system-wide limit
+    /// (`SO_RCVBUFFORCE`).
+    ///
+    /// This requires the `CAP_NET_ADMIN` capability.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_recv_buffer_size_force(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_RCVBUFFORCE, size)
+    }
+
+    /// Sets the minimum number of bytes to process for receive operations
+    /// (`SO_RCVLOWAT`).
+    pub fn set_recv_low_water(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_RCVLOWAT, size)
+    }
+
+    /// Returns the minimum number of bytes to process for receive operations
+    /// (`SO_RCVLOWAT`).
+    pub fn recv_low_water(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_RCVLOWAT)
+    }
+
+    /// Sets the minimum number of bytes to process for send operations
+    /// (`SO_SNDLOWAT`).
+    ///
+    /// Linux doesn't allow this to be changed, and returns an error.
+    pub fn set_send_low_water(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_SNDLOWAT, size)
+    }
+
+    /// Returns the minimum number of bytes to process for send operations
+    /// (`SO_SNDLOWAT`).
+    pub fn send_low_water(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_SNDLOWAT)
+    }
+
     /// Shut down the read, write, or both halves of this connection.
     ///
     /// This function will cause all pending and future I/O calls on the
@@ -142,6 +839,141 @@ impl UnixDatagram {
     }
 }
 
+/// Information about a datagram received by `UnixDatagram::recv_many`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecvMeta {
+    /// The number of bytes received into the buffer.
+    pub len: usize,
+    /// Whether the datagram was larger than the buffer, in which case the
+    /// rest of it was discarded.
+    pub truncated: bool,
+    /// The address of the sender.
+    pub addr: UnixSocketAddr,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Default for RecvMeta {
+    fn default() -> RecvMeta {
+        RecvMeta {
+            len: 0,
+            truncated: false,
+            addr: UnixSocketAddr::unnamed(),
+        }
+    }
+}
+
+/// A builder for a `UnixDatagram`, allowing the socket to be configured as
+/// it's bound.
+///
+/// Unlike a listener, a datagram socket can receive messages as soon as it's
+/// bound, so on Linux the socket file is created with no permissions at all
+/// and only given the requested mode and owner afterwards. On other
+/// platforms the file briefly has the permissions implied by the umask.
+#[derive(Clone, Debug, Default)]
+pub struct UnixDatagramBuilder {
+    buffer_sizes: BufferSizes,
+    perms: FilePerms,
+    existing: Existing,
+}
+
+impl UnixDatagramBuilder {
+    /// Creates a builder with the same defaults as `UnixDatagram::bind`.
+    pub fn new() -> UnixDatagramBuilder {
+        UnixDatagramBuilder::default()
+    }
+
+    /// Sets the `SO_SNDBUF` option.
+    pub fn send_buffer_size(&mut self, size: usize) -> &mut UnixDatagramBuilder {
+        self.buffer_sizes.send = Some(size);
+        self
+    }
+
+    /// Sets the `SO_RCVBUF` option.
+    pub fn recv_buffer_size(&mut self, size: usize) -> &mut UnixDatagramBuilder {
+        self.buffer_sizes.recv = Some(size);
+        self
+    }
+
+    /// Sets the permissions of the socket file, such as `0o660`.
+    ///
+    /// Sending to a socket requires write permission on its file. This only
+    /// applies to pathname addresses; binding to an abstract address with a
+    /// mode set fails.
+    pub fn mode(&mut self, mode: u32) -> &mut UnixDatagramBuilder {
+        self.perms.mode = Some(mode as libc::mode_t);
+        self
+    }
+
+    /// Sets the owner and group of the socket file, leaving either unchanged
+    /// if `None`.
+    pub fn owner(&mut self,
+                 uid: Option<libc::uid_t>,
+                 gid: Option<libc::gid_t>) -> &mut UnixDatagramBuilder {
+        self.perms.uid = uid;
+        self.perms.gid = gid;
+        self
+    }
+
+    /// Sets whether an existing socket file at the path is removed before
+    /// binding.
+    ///
+    /// Files which aren't sockets are never removed, and cause binding to
+    /// fail instead. Defaults to `false`.
+    pub fn unlink_existing(&mut self, unlink: bool) -> &mut UnixDatagramBuilder {
+        self.existing.set(Existing::Unlink, unlink);
+        self
+    }
+
+    /// Sets whether an existing socket file at the path is removed before
+    /// binding if it's stale, that is if connecting to it is refused.
+    ///
+    /// Unlike `unlink_existing`, this never removes the socket of a process
+    /// which is still running, in which case binding fails with `AddrInUse`.
+    /// Files which aren't sockets are never removed either. Defaults to
+    /// `false`.
+    ///
+    /// If both this and `unlink_existing` are enabled, whichever was enabled
+    /// last takes effect. Disabling one of them doesn't affect the other.
+    pub fn reclaim_stale(&mut self, reclaim: bool) -> &mut UnixDatagramBuilder {
+        self.existing.set(Existing::Reclaim, reclaim);
+        self
+    }
+
+    /// Creates a Unix datagram socket bound to the given path.
+    pub fn bind<P: AsRef<Path>>(&self, path: P) -> io::Result<UnixDatagram> {
+        socket::with_sockaddr_un(path.as_ref(), |addr, len| self.bind_raw(addr, len))
+    }
+
+    /// Creates a Unix datagram socket bound to the socket named `name` in the
+    /// directory `dir`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn bind_at<D: AsRawFd, P: AsRef<Path>>(&self,
+                                               dir: &D,
+                                               name: P) -> io::Result<UnixDatagram> {
+        socket::with_sockaddr_un_at(dir.as_raw_fd(), name.as_ref(), |addr, len| {
+            self.bind_raw(addr, len)
+        })
+    }
+
+    /// Creates a Unix datagram socket bound to the given address.
+    pub fn bind_addr(&self, addr: &UnixSocketAddr) -> io::Result<UnixDatagram> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        self.bind_raw(&addr, len)
+    }
+
+    fn bind_raw(&self,
+                addr: &libc::sockaddr_un,
+                len: libc::socklen_t) -> io::Result<UnixDatagram> {
+        let fd = Socket::new(libc::SOCK_DGRAM)?;
+        self.buffer_sizes.apply(fd.fd())?;
+        perms::bind(fd.fd(), libc::SOCK_DGRAM, addr, len, &self.perms, self.existing)?;
+
+        Ok(unsafe { UnixDatagram::from_raw_fd(fd.into_fd()) })
+    }
+}
+
+#[cfg(feature = "mio-06")]
 impl Evented for UnixDatagram {
     fn register(&self,
                 poll: &Poll,
@@ -164,6 +996,23 @@ impl Evented for UnixDatagram {
     }
 }
 
+#[cfg(feature = "mio-08")]
+impl mio08::event::Source for UnixDatagram {
+    fn register(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                  -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
+
 impl AsRawFd for UnixDatagram {
     fn as_raw_fd(&self) -> i32 {
         self.inner.as_raw_fd()
@@ -181,3 +1030,11 @@ impl FromRawFd for UnixDatagram {
         UnixDatagram { inner: net::UnixDatagram::from_raw_fd(fd) }
     }
 }
+
+fn fds_ancillary(fds: &[RawFd]) -> SocketAncillary {
+    let mut ancillary = SocketAncillary::with_capacity(SocketAncillary::space_for_fds(fds.len()));
+    if !fds.is_empty() {
+        ancillary.add_fds(fds);
+    }
+    ancillary
+}
@@ -0,0 +1,133 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use addr::UnixSocketAddr;
+
+/// A detailed error from setting up a Unix socket.
+///
+/// Functions in this crate still return `io::Error`, wrapping one of these
+/// where more detail is available. It can be recovered with
+/// `SocketError::from_io`:
+///
+/// ```no_run
+/// use mio_uds::{SocketError, UnixListener};
+///
+/// match UnixListener::bind("/some/very/long/path") {
+///     Ok(_) => {}
+///     Err(e) => match SocketError::from_io(&e) {
+///         Some(&SocketError::PathTooLong { ref path, len, max }) => {
+///             eprintln!("{} is {} bytes, but at most {} fit", path.display(), len, max);
+///         }
+///         _ => eprintln!("failed to bind: {}", e),
+///     },
+/// }
+/// ```
+///
+/// Errors from system calls are wrapped as `SocketError::Syscall`, keeping
+/// the kind of the error reported by the system. That error is also the
+/// `source` of the returned `io::Error`, and its code is available through
+/// `SocketError::raw_os_error`.
+#[derive(Debug)]
+pub enum SocketError {
+    /// A path doesn't fit in a socket address.
+    PathTooLong {
+        /// The offending path. Abstract addresses start with a nul byte.
+        path: PathBuf,
+        /// The length of the path in bytes.
+        len: usize,
+        /// The maximum length of a path in bytes.
+        max: usize,
+    },
+    /// A pathname contains a nul byte, which would cut it short.
+    InteriorNul {
+        /// The offending path.
+        path: PathBuf,
+    },
+    /// A system call failed.
+    Syscall {
+        /// The name of the system call, such as `"bind"`.
+        syscall: &'static str,
+        /// The address it was called with, if any.
+        addr: Option<UnixSocketAddr>,
+        /// The error reported by the system.
+        error: io::Error,
+    },
+}
+
+impl SocketError {
+    /// Returns the `SocketError` wrapped by `err`, if any.
+    pub fn from_io(err: &io::Error) -> Option<&SocketError> {
+        err.get_ref().and_then(|e| e.downcast_ref())
+    }
+
+    /// Returns the OS error code, if this error was reported by a system call.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match *self {
+            SocketError::Syscall { ref error, .. } => error.raw_os_error(),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the system call which failed, such as `"bind"`,
+    /// if this error was reported by one.
+    pub fn syscall(&self) -> Option<&'static str> {
+        match *self {
+            SocketError::Syscall { syscall, .. } => Some(syscall),
+            _ => None,
+        }
+    }
+
+    fn kind(&self) -> io::ErrorKind {
+        match *self {
+            SocketError::PathTooLong { .. } |
+            SocketError::InteriorNul { .. } => io::ErrorKind::InvalidInput,
+            SocketError::Syscall { ref error, .. } => error.kind(),
+        }
+    }
+}
+
+impl fmt::Display for SocketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SocketError::PathTooLong { ref path, len, max } => {
+                write!(f, "socket path {:?} is {} bytes long, but must be at most {} bytes",
+                       path, len, max)
+            }
+            SocketError::InteriorNul { ref path } => {
+                write!(f, "socket path {:?} contains a nul byte", path)
+            }
+            SocketError::Syscall { syscall, addr: Some(ref addr), ref error } => {
+                write!(f, "{} on {} failed: {}", syscall, addr, error)
+            }
+            SocketError::Syscall { syscall, addr: None, ref error } => {
+                write!(f, "{} failed: {}", syscall, error)
+            }
+        }
+    }
+}
+
+impl error::Error for SocketError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            SocketError::Syscall { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<SocketError> for io::Error {
+    /// Wraps the error in an `io::Error` of the same kind.
+    fn from(err: SocketError) -> io::Error {
+        io::Error::new(err.kind(), err)
+    }
+}
+
+/// Wraps `error` from the system call `syscall` in a `SocketError`, keeping
+/// its kind.
+pub fn syscall(syscall: &'static str,
+               addr: Option<UnixSocketAddr>,
+               error: io::Error) -> io::Error {
+    SocketError::Syscall { syscall, addr, error }.into()
+}
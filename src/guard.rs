@@ -0,0 +1,115 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::os::unix::prelude::*;
+use std::path::{Path, PathBuf};
+
+use addr;
+use socket;
+
+/// A guard which removes the file of a socket bound to a pathname when it's
+/// dropped.
+///
+/// The device, inode and change time of the file are recorded when the guard
+/// is created, and the file is only removed if the path still refers to that
+/// same file. This way a socket file which has since been replaced, for
+/// example by the next instance of a server during a rolling restart, is
+/// left alone.
+///
+/// The change time is compared as well since inode numbers of removed files
+/// are quickly reused on some filesystems. This means that the file is also
+/// left alone if its mode or owner are changed after creating the guard.
+///
+/// Errors while removing the file are ignored.
+#[derive(Debug)]
+pub struct SocketFileGuard {
+    path: PathBuf,
+    id: FileId,
+    armed: bool,
+}
+
+impl SocketFileGuard {
+    /// Creates a guard for the socket file at `path`.
+    ///
+    /// Relative paths are resolved against the current directory right away.
+    /// Returns an error if there is no socket file at the path.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<SocketFileGuard> {
+        let path = env::current_dir()?.join(path);
+        let meta = fs::symlink_metadata(&path)?;
+        if !meta.file_type().is_socket() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "file is not a socket"))
+        }
+        Ok(SocketFileGuard {
+            id: FileId::new(&meta),
+            path,
+            armed: true,
+        })
+    }
+
+    /// Creates a guard for the file that `socket` is bound to.
+    ///
+    /// This should be called right after binding, before anything else has
+    /// had a chance to replace the file. Returns an error if the socket isn't
+    /// bound to a pathname.
+    ///
+    /// Sockets bound to paths too long for a socket address, or with
+    /// `bind_at`, report an address through `/proc/self/fd` which no longer
+    /// leads to the file, so they're rejected as well. Use `new` with the
+    /// path the socket was bound to instead.
+    pub fn for_socket<S: AsRawFd>(socket: &S) -> io::Result<SocketFileGuard> {
+        let addr = addr::local_addr(socket.as_raw_fd())?;
+        match addr.as_pathname() {
+            Some(path) if socket::is_dir_fd_path(path) => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                   "socket was bound through a directory file descriptor"))
+            }
+            Some(path) => SocketFileGuard::new(path),
+            None => {
+                Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                   "socket is not bound to a pathname"))
+            }
+        }
+    }
+
+    /// Returns the absolute path of the guarded socket file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Consumes the guard without removing the file.
+    pub fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for SocketFileGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return
+        }
+        if let Ok(meta) = fs::symlink_metadata(&self.path) {
+            if FileId::new(&meta) == self.id {
+                let _ = fs::remove_file(&self.path);
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct FileId {
+    dev: u64,
+    ino: u64,
+    ctime: i64,
+    ctime_nsec: i64,
+}
+
+impl FileId {
+    fn new(meta: &fs::Metadata) -> FileId {
+        FileId {
+            dev: meta.dev(),
+            ino: meta.ino(),
+            ctime: meta.ctime(),
+            ctime_nsec: meta.ctime_nsec(),
+        }
+    }
+}
@@ -2,22 +2,49 @@
 
 #![cfg(unix)]
 #![deny(missing_docs)]
-#![doc(html_root_url = "https://docs.rs/mio-uds/0.6")]
+#![doc(html_root_url = "https://docs.rs/mio-uds/0.7")]
 
+#[cfg(feature = "iovec")]
 extern crate iovec;
 extern crate libc;
+#[cfg(feature = "mio-06")]
 extern crate mio;
+#[cfg(feature = "mio-08")]
+extern crate mio08;
+#[cfg(feature = "tokio")]
+extern crate tokio1;
 
 use std::io;
 
+mod addr;
+pub mod ancillary;
+mod connect;
 mod datagram;
+mod error;
+mod guard;
 mod listener;
+mod perms;
+mod seqpacket;
 mod socket;
 mod stream;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod ucred;
 
+pub use addr::UnixSocketAddr;
+#[cfg(feature = "mio-06")]
+pub use connect::PendingConnect;
+pub use error::SocketError;
 pub use stream::UnixStream;
-pub use listener::UnixListener;
-pub use datagram::UnixDatagram;
+pub use listener::{UnixListener, UnixListenerBuilder};
+pub use datagram::{UnixDatagram, UnixDatagramBuilder};
+pub use guard::SocketFileGuard;
+pub use seqpacket::{UnixSeqpacket, UnixSeqpacketListener};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use ucred::UCred;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use datagram::RecvMeta;
 
 fn cvt(i: libc::c_int) -> io::Result<libc::c_int> {
     if i == -1 {
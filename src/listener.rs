@@ -1,16 +1,29 @@
+use std::cmp;
+use std::fs;
 use std::io;
 use std::os::unix::net;
 use std::os::unix::prelude::*;
 use std::path::Path;
+use std::time::Duration;
 
 use libc;
+#[cfg(feature = "mio-06")]
 use mio::event::Evented;
+#[cfg(feature = "mio-06")]
 use mio::unix::EventedFd;
+#[cfg(feature = "mio-06")]
 use mio::{Poll, PollOpt, Ready, Token};
+#[cfg(feature = "mio-08")]
+use mio08::{self, Interest, Registry};
+#[cfg(feature = "mio-08")]
+use mio08::unix::SourceFd;
 
-use UnixStream;
+use {UnixSeqpacketListener, UnixStream};
+use addr::{self, UnixSocketAddr};
 use cvt;
-use socket::{sockaddr_un, Socket};
+use error;
+use perms::{self, Existing, FilePerms};
+use socket::{self, BufferSizes, Socket};
 
 /// A structure representing a Unix domain socket server.
 ///
@@ -20,6 +33,7 @@ use socket::{sockaddr_un, Socket};
 #[derive(Debug)]
 pub struct UnixListener {
     inner: net::UnixListener,
+    accepted: BufferSizes,
 }
 
 impl UnixListener {
@@ -29,16 +43,44 @@ impl UnixListener {
     }
 
     fn _bind(path: &Path) -> io::Result<UnixListener> {
-        unsafe {
-            let (addr, len) = try!(sockaddr_un(path));
-            let fd = try!(Socket::new(libc::SOCK_STREAM));
+        socket::with_sockaddr_un(path, UnixListener::bind_raw)
+    }
 
-            let addr = &addr as *const _ as *const _;
-            try!(cvt(libc::bind(fd.fd(), addr, len)));
-            try!(cvt(libc::listen(fd.fd(), 128)));
+    /// Creates a new `UnixListener` bound to the socket named `name` in the
+    /// directory `dir`.
+    ///
+    /// This makes it possible to bind to paths which are too long to fit in
+    /// a socket address, which `bind` also falls back to automatically. The
+    /// address is formed through `/proc/self/fd`, which is also what
+    /// `local_addr` will report, so `SocketFileGuard::for_socket` can't be
+    /// used with the socket.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn bind_at<D: AsRawFd, P: AsRef<Path>>(dir: &D, name: P) -> io::Result<UnixListener> {
+        socket::with_sockaddr_un_at(dir.as_raw_fd(), name.as_ref(), UnixListener::bind_raw)
+    }
 
-            Ok(UnixListener::from_raw_fd(fd.into_fd()))
-        }
+    /// Creates a new `UnixListener` bound to the specified socket, first
+    /// removing the socket file left behind by a previous listener at that
+    /// path if nothing is listening on it any more.
+    ///
+    /// If another process is still listening on the path, or the path exists
+    /// but isn't a socket, an error of kind `AddrInUse` is returned and the
+    /// file is left alone.
+    pub fn bind_reclaiming<P: AsRef<Path>>(path: P) -> io::Result<UnixListener> {
+        UnixListenerBuilder::new().reclaim_stale(true).bind(path)
+    }
+
+    /// Creates a new `UnixListener` bound to the specified address.
+    ///
+    /// This is the same as `bind`, except that abstract addresses can be
+    /// bound to directly rather than through paths starting with a nul byte.
+    pub fn bind_addr(addr: &UnixSocketAddr) -> io::Result<UnixListener> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        UnixListener::bind_raw(&addr, len)
+    }
+
+    fn bind_raw(addr: &libc::sockaddr_un, len: libc::socklen_t) -> io::Result<UnixListener> {
+        UnixListenerBuilder::new().bind_raw(addr, len)
     }
 
     /// Consumes a standard library `UnixListener` and returns a wrapped
@@ -48,7 +90,7 @@ impl UnixListener {
     /// ready to get associated with an event loop.
     pub fn from_listener(stream: net::UnixListener) -> io::Result<UnixListener> {
         try!(stream.set_nonblocking(true));
-        Ok(UnixListener { inner: stream })
+        Ok(UnixListener { inner: stream, accepted: BufferSizes::default() })
     }
 
     /// Accepts a new incoming connection to this listener.
@@ -57,6 +99,9 @@ impl UnixListener {
     /// address will be returned as `Ok(Some(...))`. If there is no connection
     /// waiting to be accepted, then `Ok(None)` is returned.
     ///
+    /// The credentials of the connecting process can be retrieved from the
+    /// returned stream with `peer_cred` before any data is read from it.
+    ///
     /// If an error happens while accepting, `Err` is returned.
     pub fn accept(&self) -> io::Result<Option<(UnixStream, net::SocketAddr)>> {
         match try!(self.accept_std()) {
@@ -65,6 +110,21 @@ impl UnixListener {
         }
     }
 
+    /// Accepts a new incoming connection to this listener.
+    ///
+    /// This method is the same as `accept`, except that the remote peer's
+    /// address is returned as a `UnixSocketAddr`.
+    pub fn accept_unix_addr(&self) -> io::Result<Option<(UnixStream, UnixSocketAddr)>> {
+        match socket::accept(self.inner.as_raw_fd()) {
+            Ok((socket, addr)) => {
+                self.accepted.apply(socket.fd())?;
+                Ok(Some((unsafe { UnixStream::from_raw_fd(socket.into_fd()) }, addr)))
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Accepts a new incoming connection to this listener.
     ///
     /// This method is the same as `accept`, except that it returns a `net::UnixStream` *in blocking mode*
@@ -74,9 +134,10 @@ impl UnixListener {
     /// If an error happens while accepting, `Err` is returned.
     pub fn accept_std(&self) -> io::Result<Option<(net::UnixStream, net::SocketAddr)>> {
         match self.inner.accept() {
-            Ok((socket, addr)) => Ok(Some(unsafe {
-                (net::UnixStream::from_raw_fd(socket.into_raw_fd()), addr)
-            })),
+            Ok((socket, addr)) => {
+                self.accepted.apply(socket.as_raw_fd())?;
+                Ok(Some(unsafe { (net::UnixStream::from_raw_fd(socket.into_raw_fd()), addr) }))
+            }
             Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
             Err(e) => Err(e),
         }
@@ -88,7 +149,7 @@ impl UnixListener {
     /// object references. Both handles can be used to accept incoming
     /// connections and options set on one listener will affect the other.
     pub fn try_clone(&self) -> io::Result<UnixListener> {
-        self.inner.try_clone().map(|l| UnixListener { inner: l })
+        self.inner.try_clone().map(|l| UnixListener { inner: l, accepted: self.accepted })
     }
 
     /// Returns the local socket address of this listener.
@@ -96,12 +157,310 @@ impl UnixListener {
         self.inner.local_addr()
     }
 
+    /// Returns the local socket address of this listener as a
+    /// `UnixSocketAddr`.
+    pub fn local_unix_addr(&self) -> io::Result<UnixSocketAddr> {
+        addr::local_addr(self.inner.as_raw_fd())
+    }
+
     /// Returns the value of the `SO_ERROR` option.
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         self.inner.take_error()
     }
+
+    /// Sets the size of the send buffer (`SO_SNDBUF`).
+    ///
+    /// The kernel caps this, on Linux to `net.core.wmem_max`, and may double
+    /// the value to leave room for bookkeeping.
+    ///
+    /// Streams accepted from this listener inherit the setting on some
+    /// platforms, but not on Linux, where it has to be set on them after
+    /// accepting. Sizes set through `UnixListenerBuilder` are set on
+    /// accepted streams by this crate instead.
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_SNDBUF, size)
+    }
+
+    /// Returns the size of the send buffer (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_SNDBUF)
+    }
+
+    /// Sets the size of the receive buffer (`SO_RCVBUF`).
+    ///
+    /// The kernel caps this, on Linux to `net.core.rmem_max`, and may double
+    /// the value to leave room for bookkeeping.
+    ///
+    /// Streams accepted from this listener inherit the setting on some
+    /// platforms, but not on Linux, where it has to be set on them after
+    /// accepting. Sizes set through `UnixListenerBuilder` are set on
+    /// accepted streams by this crate instead.
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_RCVBUF, size)
+    }
+
+    /// Returns the size of the receive buffer (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_RCVBUF)
+    }
+
+    /// Sets the size of the send buffer ignoring the system-wide limit
+    /// (`SO_SNDBUFFORCE`).
+    ///
+    /// This requires the `CAP_NET_ADMIN` capability.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_send_buffer_size_force(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_SNDBUFFORCE, size)
+    }
+
+    /// Sets the size of the receive buffer ignoring the system-wide limit
+    /// (`SO_RCVBUFFORCE`).
+    ///
+    /// This requires the `CAP_NET_ADMIN` capability.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_recv_buffer_size_force(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_RCVBUFFORCE, size)
+    }
+
+    /// Sets the minimum number of bytes to process for receive operations
+    /// (`SO_RCVLOWAT`).
+    ///
+    /// Streams accepted from this listener inherit the setting on some
+    /// platforms, but not on Linux, where it has to be set on them after
+    /// accepting.
+    pub fn set_recv_low_water(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_RCVLOWAT, size)
+    }
+
+    /// Returns the minimum number of bytes to process for receive operations
+    /// (`SO_RCVLOWAT`).
+    pub fn recv_low_water(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_RCVLOWAT)
+    }
+
+    /// Sets the minimum number of bytes to process for send operations
+    /// (`SO_SNDLOWAT`).
+    ///
+    /// Linux doesn't allow this to be changed, and returns an error.
+    pub fn set_send_low_water(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_SNDLOWAT, size)
+    }
+
+    /// Returns the minimum number of bytes to process for send operations
+    /// (`SO_SNDLOWAT`).
+    pub fn send_low_water(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_SNDLOWAT)
+    }
+
+    /// Blocks until a connection is ready to be accepted, or until `timeout`
+    /// has passed if it's not `None`.
+    ///
+    /// This is useful for simple programs which don't otherwise need an
+    /// event loop. An error of kind `TimedOut` is returned if the timeout
+    /// passes first.
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        socket::wait(self.as_raw_fd(), libc::POLLIN, timeout)
+    }
+}
+
+/// A builder for a `UnixListener`, allowing the socket to be configured
+/// before it starts listening for connections.
+///
+/// The same options apply to a `UnixSeqpacketListener` created with
+/// `bind_seqpacket`.
+///
+/// Connections are refused until the listener is fully set up, so options
+/// such as the file mode of the socket are in effect before any client can
+/// connect.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mio_uds::UnixListenerBuilder;
+///
+/// let listener = UnixListenerBuilder::new()
+///     .backlog(1024)
+///     .mode(0o660)
+///     .unlink_existing(true)
+///     .bind("/run/my-daemon.sock")
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct UnixListenerBuilder {
+    backlog: libc::c_int,
+    buffer_sizes: BufferSizes,
+    perms: FilePerms,
+    existing: Existing,
+}
+
+impl UnixListenerBuilder {
+    /// Creates a builder with the same defaults as `UnixListener::bind`.
+    pub fn new() -> UnixListenerBuilder {
+        UnixListenerBuilder {
+            backlog: 128,
+            buffer_sizes: BufferSizes::default(),
+            perms: FilePerms::default(),
+            existing: Existing::Keep,
+        }
+    }
+
+    /// Sets the maximum number of pending connections, passed to `listen`.
+    ///
+    /// The kernel may silently cap this, on Linux to `net.core.somaxconn`.
+    /// Defaults to 128.
+    pub fn backlog(&mut self, backlog: u32) -> &mut UnixListenerBuilder {
+        self.backlog = cmp::min(backlog, libc::c_int::MAX as u32) as libc::c_int;
+        self
+    }
+
+    /// Sets the `SO_SNDBUF` option.
+    ///
+    /// The same size is set on streams accepted from the listener, since
+    /// not all platforms carry it over.
+    pub fn send_buffer_size(&mut self, size: usize) -> &mut UnixListenerBuilder {
+        self.buffer_sizes.send = Some(size);
+        self
+    }
+
+    /// Sets the `SO_RCVBUF` option.
+    ///
+    /// The same size is set on streams accepted from the listener, since
+    /// not all platforms carry it over.
+    pub fn recv_buffer_size(&mut self, size: usize) -> &mut UnixListenerBuilder {
+        self.buffer_sizes.recv = Some(size);
+        self
+    }
+
+    /// Sets the permissions of the socket file, such as `0o660`.
+    ///
+    /// Connecting to a socket requires write permission on its file. This
+    /// only applies to pathname addresses; binding to an abstract address
+    /// with a mode set fails.
+    ///
+    /// The mode is set explicitly after binding, so the process umask doesn't
+    /// affect it.
+    pub fn mode(&mut self, mode: u32) -> &mut UnixListenerBuilder {
+        self.perms.mode = Some(mode as libc::mode_t);
+        self
+    }
+
+    /// Sets the owner and group of the socket file, leaving either unchanged
+    /// if `None`.
+    ///
+    /// Changing the owner generally requires privileges, while the group can
+    /// be set to any group the process is a member of. As with `mode`, this
+    /// only applies to pathname addresses.
+    pub fn owner(&mut self,
+                 uid: Option<libc::uid_t>,
+                 gid: Option<libc::gid_t>) -> &mut UnixListenerBuilder {
+        self.perms.uid = uid;
+        self.perms.gid = gid;
+        self
+    }
+
+    /// Sets whether an existing socket file at the path is removed before
+    /// binding.
+    ///
+    /// This is useful for cleaning up after a previous instance of a server
+    /// which didn't exit cleanly. Files which aren't sockets are never
+    /// removed, and cause binding to fail instead. Defaults to `false`.
+    pub fn unlink_existing(&mut self, unlink: bool) -> &mut UnixListenerBuilder {
+        self.existing.set(Existing::Unlink, unlink);
+        self
+    }
+
+    /// Sets whether an existing socket file at the path is removed before
+    /// binding if it's stale, that is if connecting to it is refused.
+    ///
+    /// Unlike `unlink_existing`, this never removes the socket of a process
+    /// which is still running, in which case binding fails with `AddrInUse`.
+    /// Files which aren't sockets are never removed either. Defaults to
+    /// `false`.
+    ///
+    /// If both this and `unlink_existing` are enabled, whichever was enabled
+    /// last takes effect. Disabling one of them doesn't affect the other.
+    pub fn reclaim_stale(&mut self, reclaim: bool) -> &mut UnixListenerBuilder {
+        self.existing.set(Existing::Reclaim, reclaim);
+        self
+    }
+
+    /// Creates a new `UnixListener` bound to the specified socket.
+    pub fn bind<P: AsRef<Path>>(&self, path: P) -> io::Result<UnixListener> {
+        socket::with_sockaddr_un(path.as_ref(), |addr, len| self.bind_raw(addr, len))
+    }
+
+    /// Creates a new `UnixListener` bound to the socket named `name` in the
+    /// directory `dir`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn bind_at<D: AsRawFd, P: AsRef<Path>>(&self,
+                                               dir: &D,
+                                               name: P) -> io::Result<UnixListener> {
+        socket::with_sockaddr_un_at(dir.as_raw_fd(), name.as_ref(), |addr, len| {
+            self.bind_raw(addr, len)
+        })
+    }
+
+    /// Creates a new `UnixListener` bound to the specified address.
+    pub fn bind_addr(&self, addr: &UnixSocketAddr) -> io::Result<UnixListener> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        self.bind_raw(&addr, len)
+    }
+
+    /// Creates a new `UnixSeqpacketListener` bound to the specified socket.
+    pub fn bind_seqpacket<P: AsRef<Path>>(&self, path: P) -> io::Result<UnixSeqpacketListener> {
+        socket::with_sockaddr_un(path.as_ref(), |addr, len| self.bind_seqpacket_raw(addr, len))
+    }
+
+    /// Creates a new `UnixSeqpacketListener` bound to the specified address.
+    pub fn bind_seqpacket_addr(&self, addr: &UnixSocketAddr) -> io::Result<UnixSeqpacketListener> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        self.bind_seqpacket_raw(&addr, len)
+    }
+
+    fn bind_raw(&self,
+                addr: &libc::sockaddr_un,
+                len: libc::socklen_t) -> io::Result<UnixListener> {
+        let fd = self.listen_raw(libc::SOCK_STREAM, addr, len)?;
+        let mut listener = unsafe { UnixListener::from_raw_fd(fd.into_fd()) };
+        listener.accepted = self.buffer_sizes;
+        Ok(listener)
+    }
+
+    pub(crate) fn bind_seqpacket_raw(&self,
+                                     addr: &libc::sockaddr_un,
+                                     len: libc::socklen_t)
+                                     -> io::Result<UnixSeqpacketListener> {
+        let fd = self.listen_raw(libc::SOCK_SEQPACKET, addr, len)?;
+        let mut listener = unsafe { UnixSeqpacketListener::from_raw_fd(fd.into_fd()) };
+        listener.accepted = self.buffer_sizes;
+        Ok(listener)
+    }
+
+    fn listen_raw(&self,
+                  ty: libc::c_int,
+                  addr: &libc::sockaddr_un,
+                  len: libc::socklen_t) -> io::Result<Socket> {
+        let fd = Socket::new(ty)?;
+        self.buffer_sizes.apply(fd.fd())?;
+
+        let path = perms::bind(fd.fd(), ty, addr, len, &self.perms, self.existing)?;
+        if let Err(e) = unsafe { cvt(libc::listen(fd.fd(), self.backlog)) } {
+            if let Some(path) = path {
+                let _ = fs::remove_file(path);
+            }
+            return Err(error::syscall("listen", socket::err_addr(addr, len), e))
+        }
+        Ok(fd)
+    }
+}
+
+impl Default for UnixListenerBuilder {
+    fn default() -> UnixListenerBuilder {
+        UnixListenerBuilder::new()
+    }
 }
 
+#[cfg(feature = "mio-06")]
 impl Evented for UnixListener {
     fn register(&self, poll: &Poll, token: Token, events: Ready, opts: PollOpt) -> io::Result<()> {
         EventedFd(&self.as_raw_fd()).register(poll, token, events, opts)
@@ -122,6 +481,23 @@ impl Evented for UnixListener {
     }
 }
 
+#[cfg(feature = "mio-08")]
+impl mio08::event::Source for UnixListener {
+    fn register(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                  -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
+
 impl AsRawFd for UnixListener {
     fn as_raw_fd(&self) -> i32 {
         self.inner.as_raw_fd()
@@ -138,6 +514,7 @@ impl FromRawFd for UnixListener {
     unsafe fn from_raw_fd(fd: i32) -> UnixListener {
         UnixListener {
             inner: net::UnixListener::from_raw_fd(fd),
+            accepted: BufferSizes::default(),
         }
     }
 }
@@ -0,0 +1,227 @@
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::mem;
+use std::os::unix::prelude::*;
+use std::path::{Path, PathBuf};
+
+use libc;
+
+use addr::UnixSocketAddr;
+use cvt;
+use error;
+use socket::{self, Socket};
+
+/// The mode and ownership to give the file created when binding a socket to
+/// a pathname.
+#[derive(Clone, Debug, Default)]
+pub struct FilePerms {
+    pub mode: Option<libc::mode_t>,
+    pub uid: Option<libc::uid_t>,
+    pub gid: Option<libc::gid_t>,
+}
+
+impl FilePerms {
+    fn is_empty(&self) -> bool {
+        self.mode.is_none() && self.uid.is_none() && self.gid.is_none()
+    }
+
+    /// Restricts the permissions of the file which binding `fd` will create,
+    /// so that nobody else can use it until `apply` has been called.
+    ///
+    /// Returns the mode the file would otherwise have been created with, for
+    /// `apply` to restore if no mode has been set.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn prepare(&self, fd: libc::c_int) -> io::Result<Option<libc::mode_t>> {
+        // Linux creates the socket file with the mode of the socket's inode,
+        // masked by the umask as usual.
+        if self.is_empty() {
+            return Ok(None)
+        }
+        unsafe {
+            let created_mode = match self.mode {
+                Some(_) => None,
+                None => {
+                    let mut stat: libc::stat = mem::zeroed();
+                    cvt(libc::fstat(fd, &mut stat))?;
+                    Some(stat.st_mode & 0o7777 & !umask())
+                }
+            };
+            cvt(libc::fchmod(fd, 0))?;
+            Ok(created_mode)
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn prepare(&self, _fd: libc::c_int) -> io::Result<Option<libc::mode_t>> {
+        Ok(None)
+    }
+
+    /// Gives the socket file at `path` its mode and owner, falling back to
+    /// `created_mode` if no mode has been set.
+    ///
+    /// The file is checked to still be a socket first, and the owner is set
+    /// without following symbolic links, so that a file swapped for a link
+    /// pointing elsewhere since binding is left alone.
+    fn apply(&self, path: &Path, created_mode: Option<libc::mode_t>) -> io::Result<()> {
+        if self.is_empty() {
+            return Ok(())
+        }
+        if !fs::symlink_metadata(path)?.file_type().is_socket() {
+            return Err(io::Error::new(io::ErrorKind::Other,
+                                      "socket file was replaced before setting its permissions"))
+        }
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        unsafe {
+            if self.uid.is_some() || self.gid.is_some() {
+                // -1 leaves the corresponding ID unchanged.
+                let uid = self.uid.unwrap_or(!0);
+                let gid = self.gid.unwrap_or(!0);
+                cvt(libc::fchownat(libc::AT_FDCWD, path.as_ptr(), uid, gid,
+                                   libc::AT_SYMLINK_NOFOLLOW))?;
+            }
+            // `fchmodat` with `AT_SYMLINK_NOFOLLOW` isn't supported by older
+            // C libraries, hence checking for a socket above instead.
+            if let Some(mode) = self.mode.or(created_mode) {
+                cvt(libc::chmod(path.as_ptr(), mode))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the umask of this process.
+///
+/// It's read from `/proc/self/status` where possible, since the only other
+/// way to get it is to change it and back, which races with other threads
+/// creating files.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn umask() -> libc::mode_t {
+    let mask = fs::read_to_string("/proc/self/status").ok().and_then(|status| {
+        status.lines()
+            .filter_map(|line| line.strip_prefix("Umask:"))
+            .filter_map(|mask| libc::mode_t::from_str_radix(mask.trim(), 8).ok())
+            .next()
+    });
+    match mask {
+        Some(mask) => mask,
+        // The `Umask` field needs Linux 4.7. Meanwhile files created by
+        // other threads get a restrictive mode rather than a permissive one.
+        None => unsafe {
+            let mask = libc::umask(0o077);
+            libc::umask(mask);
+            mask
+        },
+    }
+}
+
+/// What to do about a file already present at the path being bound to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Existing {
+    /// Leave it alone, so that binding fails.
+    #[default]
+    Keep,
+    /// Remove it if it's a socket.
+    Unlink,
+    /// Remove it if it's a socket which nothing is listening on.
+    Reclaim,
+}
+
+impl Existing {
+    /// Switches to `mode` if `enable` is set, or back to `Keep` if `mode` is
+    /// the one in effect, leaving any other mode alone.
+    pub fn set(&mut self, mode: Existing, enable: bool) {
+        if enable {
+            *self = mode;
+        } else if *self == mode {
+            *self = Existing::Keep;
+        }
+    }
+}
+
+/// Binds `fd`, a socket of type `ty`, to `addr`, giving the socket file the
+/// permissions in `perms` and first dealing with any existing file as
+/// described by `existing`.
+///
+/// Returns the path of the socket file, if any, which the caller should
+/// remove if it fails to finish setting up the socket.
+pub fn bind(fd: libc::c_int,
+            ty: libc::c_int,
+            addr: &libc::sockaddr_un,
+            len: libc::socklen_t,
+            perms: &FilePerms,
+            existing: Existing) -> io::Result<Option<PathBuf>> {
+    let path = UnixSocketAddr::from_raw(addr, len).as_pathname().map(|p| p.to_path_buf());
+    if !perms.is_empty() && path.is_none() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                  "file mode and owner can only be set for pathname addresses"))
+    }
+    if let Some(ref path) = path {
+        match existing {
+            Existing::Keep => {}
+            Existing::Unlink => unlink_socket(path)?,
+            Existing::Reclaim => reclaim_socket(path, ty, addr, len)?,
+        }
+    }
+
+    let created_mode = perms.prepare(fd)?;
+    unsafe {
+        cvt(libc::bind(fd, addr as *const _ as *const _, len))
+            .map_err(|e| error::syscall("bind", socket::err_addr(addr, len), e))?;
+    }
+    if let Some(ref path) = path {
+        if let Err(e) = perms.apply(path, created_mode) {
+            let _ = fs::remove_file(path);
+            return Err(e)
+        }
+    }
+    Ok(path)
+}
+
+/// Removes the socket file at `path` if there is one and connecting to it
+/// with a socket of type `ty` is refused, meaning that the process which
+/// bound it has gone away.
+///
+/// A socket which is still in use or a file which isn't a socket results in
+/// an `AddrInUse` error.
+fn reclaim_socket(path: &Path,
+                  ty: libc::c_int,
+                  addr: &libc::sockaddr_un,
+                  len: libc::socklen_t) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(ref meta) if meta.file_type().is_socket() => {}
+        Ok(_) => {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse,
+                                      "address is in use by a file which isn't a socket"))
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    }
+
+    let probe = Socket::new(ty)?;
+    let res = unsafe { cvt(libc::connect(probe.fd(), addr as *const _ as *const _, len)) };
+    match res {
+        Err(ref e) if e.raw_os_error() == Some(libc::ECONNREFUSED) => {}
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        // Connecting succeeded or is pending, or the socket is of another
+        // type, so something is still using it.
+        _ => {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse,
+                                      "address is in use by a live socket"))
+        }
+    }
+    match fs::remove_file(path) {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        res => res,
+    }
+}
+
+/// Removes the socket file at `path` if there is one.
+fn unlink_socket(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(ref meta) if meta.file_type().is_socket() => fs::remove_file(path),
+        Ok(_) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,358 @@
+use std::io;
+use std::mem;
+use std::net::Shutdown;
+use std::os::unix::prelude::*;
+use std::path::Path;
+
+use libc;
+#[cfg(feature = "mio-06")]
+use mio::event::Evented;
+#[cfg(feature = "mio-06")]
+use mio::unix::EventedFd;
+#[cfg(feature = "mio-06")]
+use mio::{Poll, PollOpt, Ready, Token};
+#[cfg(feature = "mio-08")]
+use mio08::{self, Interest, Registry};
+#[cfg(feature = "mio-08")]
+use mio08::unix::SourceFd;
+
+use UnixListenerBuilder;
+use addr::{self, UnixSocketAddr};
+use socket::{self, BufferSizes, Socket};
+
+/// A structure representing a Unix domain seqpacket socket server.
+///
+/// This listener can be used to accept new `UnixSeqpacket` connections from
+/// remote endpoints.
+#[derive(Debug)]
+pub struct UnixSeqpacketListener {
+    inner: Socket,
+    pub(crate) accepted: BufferSizes,
+}
+
+/// A Unix seqpacket socket.
+///
+/// This type represents a `SOCK_SEQPACKET` connection of the `AF_UNIX`
+/// family. Like a stream it is connection oriented and reliable, but like a
+/// datagram socket it preserves message boundaries: each `send` is delivered
+/// to the peer as a single message by one `recv`.
+///
+/// Connections can either be made to a `UnixSeqpacketListener` bound to a
+/// path, or be an ephemeral pair created by the `pair` function.
+///
+/// Note that all values of this type are typically in nonblocking mode, so
+/// the `send` and `recv` methods may return an error with the kind of
+/// `WouldBlock`, indicating that it's not ready to send/receive just yet.
+#[derive(Debug)]
+pub struct UnixSeqpacket {
+    inner: Socket,
+}
+
+impl UnixSeqpacketListener {
+    /// Creates a new `UnixSeqpacketListener` bound to the specified socket.
+    ///
+    /// This uses the defaults of `UnixListenerBuilder`, whose
+    /// `bind_seqpacket` method allows setting the backlog and other options.
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<UnixSeqpacketListener> {
+        UnixSeqpacketListener::_bind(path.as_ref())
+    }
+
+    fn _bind(path: &Path) -> io::Result<UnixSeqpacketListener> {
+        socket::with_sockaddr_un(path, UnixSeqpacketListener::bind_raw)
+    }
+
+    /// Creates a new `UnixSeqpacketListener` bound to the specified address.
+    ///
+    /// This is the same as `bind`, except that abstract addresses can be
+    /// bound to directly rather than through paths starting with a nul byte.
+    pub fn bind_addr(addr: &UnixSocketAddr) -> io::Result<UnixSeqpacketListener> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        UnixSeqpacketListener::bind_raw(&addr, len)
+    }
+
+    fn bind_raw(addr: &libc::sockaddr_un,
+                len: libc::socklen_t) -> io::Result<UnixSeqpacketListener> {
+        UnixListenerBuilder::new().bind_seqpacket_raw(addr, len)
+    }
+
+    /// Accepts a new incoming connection to this listener.
+    ///
+    /// When established, the corresponding `UnixSeqpacket` and the remote
+    /// peer's address will be returned as `Ok(Some(...))`. If there is no
+    /// connection waiting to be accepted, then `Ok(None)` is returned.
+    ///
+    /// If an error happens while accepting, `Err` is returned.
+    pub fn accept(&self) -> io::Result<Option<(UnixSeqpacket, UnixSocketAddr)>> {
+        match self.inner.accept() {
+            Ok((socket, addr)) => {
+                self.accepted.apply(socket.fd())?;
+                Ok(Some((UnixSeqpacket { inner: socket }, addr)))
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Creates a new independently owned handle to the underlying socket.
+    ///
+    /// The returned `UnixSeqpacketListener` is a reference to the same socket
+    /// that this object references. Both handles can be used to accept
+    /// incoming connections and options set on one listener will affect the
+    /// other.
+    pub fn try_clone(&self) -> io::Result<UnixSeqpacketListener> {
+        self.inner.duplicate().map(|s| UnixSeqpacketListener { inner: s, accepted: self.accepted })
+    }
+
+    /// Returns the local socket address of this listener.
+    pub fn local_addr(&self) -> io::Result<UnixSocketAddr> {
+        addr::local_addr(self.inner.fd())
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+}
+
+impl UnixSeqpacket {
+    /// Connects to the socket named by `path`.
+    ///
+    /// The socket returned may not be readable and/or writable yet, as the
+    /// connection may be in progress. The socket should be registered with an
+    /// event loop to wait on both of these properties being available.
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<UnixSeqpacket> {
+        UnixSeqpacket::_connect(path.as_ref())
+    }
+
+    fn _connect(path: &Path) -> io::Result<UnixSeqpacket> {
+        socket::with_sockaddr_un(path, UnixSeqpacket::connect_raw)
+    }
+
+    /// Connects to the socket at the specified address.
+    ///
+    /// This is the same as `connect`, except that abstract addresses can be
+    /// connected to directly rather than through paths starting with a nul
+    /// byte.
+    pub fn connect_addr(addr: &UnixSocketAddr) -> io::Result<UnixSeqpacket> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        UnixSeqpacket::connect_raw(&addr, len)
+    }
+
+    fn connect_raw(addr: &libc::sockaddr_un, len: libc::socklen_t) -> io::Result<UnixSeqpacket> {
+        let socket = Socket::new(libc::SOCK_SEQPACKET)?;
+        socket::connect(socket.fd(), addr, len)?;
+
+        Ok(UnixSeqpacket { inner: socket })
+    }
+
+    /// Creates an unnamed pair of connected sockets.
+    ///
+    /// Returns two `UnixSeqpacket`s which are connected to each other.
+    pub fn pair() -> io::Result<(UnixSeqpacket, UnixSeqpacket)> {
+        Socket::pair(libc::SOCK_SEQPACKET).map(|(a, b)| {
+            (UnixSeqpacket { inner: a }, UnixSeqpacket { inner: b })
+        })
+    }
+
+    /// Creates a new independently owned handle to the underlying socket.
+    ///
+    /// The returned `UnixSeqpacket` is a reference to the same socket that
+    /// this object references. Both handles will send and receive messages on
+    /// the same connection, and options set on one socket will be propagated
+    /// to the other.
+    pub fn try_clone(&self) -> io::Result<UnixSeqpacket> {
+        self.inner.duplicate().map(|s| UnixSeqpacket { inner: s })
+    }
+
+    /// Returns the socket address of the local half of this connection.
+    pub fn local_addr(&self) -> io::Result<UnixSocketAddr> {
+        addr::local_addr(self.inner.fd())
+    }
+
+    /// Returns the socket address of the remote half of this connection.
+    pub fn peer_addr(&self) -> io::Result<UnixSocketAddr> {
+        addr::peer_addr(self.inner.fd())
+    }
+
+    /// Sends a message to the socket's peer.
+    ///
+    /// The whole of `buf` is sent as a single message, or not at all.
+    ///
+    /// On success, returns the number of bytes written. If the socket is not
+    /// currently writable then a "would block" error is returned.
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        unsafe {
+            let rc = libc::send(self.inner.fd(),
+                                buf.as_ptr() as *const _,
+                                buf.len(),
+                                socket::MSG_NOSIGNAL);
+            if rc < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(rc as usize)
+            }
+        }
+    }
+
+    /// Receives a single message from the socket's peer.
+    ///
+    /// If the message is larger than `buf` then an error of the kind
+    /// `InvalidData` is returned, and the rest of the message is lost. Use
+    /// `recv_with_flags` to receive the start of such messages.
+    ///
+    /// On success, returns the number of bytes read, which is zero once the
+    /// peer has closed the connection. If no messages are available yet then
+    /// a "would block" error is returned.
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let (n, flags) = self.recv_with_flags(buf, 0)?;
+        if flags & libc::MSG_TRUNC != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "message was larger than the buffer"))
+        }
+        Ok(n)
+    }
+
+    /// Receives a single message from the socket's peer with the `MSG_*`
+    /// flags in `flags`, such as `libc::MSG_PEEK`.
+    ///
+    /// On success, returns the number of bytes read and the flags describing
+    /// the received message (`msg_flags`). In particular `libc::MSG_TRUNC`
+    /// is set if the message was larger than `buf`, in which case the rest of
+    /// it was discarded.
+    pub fn recv_with_flags(&self, buf: &mut [u8], flags: libc::c_int)
+                           -> io::Result<(usize, libc::c_int)> {
+        let mut addr = unsafe { mem::zeroed() };
+        socket::recv_flags(self.inner.fd(), buf, flags, &mut addr).map(|(n, flags, _)| (n, flags))
+    }
+
+    /// Returns the value of the `SO_ERROR` option.
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        self.inner.take_error()
+    }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    ///
+    /// This function will cause all pending and future I/O calls on the
+    /// specified portions to immediately return with an appropriate value
+    /// (see the documentation of `Shutdown`).
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
+    }
+}
+
+#[cfg(feature = "mio-06")]
+impl Evented for UnixSeqpacketListener {
+    fn register(&self, poll: &Poll, token: Token, events: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.as_raw_fd()).register(poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.as_raw_fd()).reregister(poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        EventedFd(&self.as_raw_fd()).deregister(poll)
+    }
+}
+
+#[cfg(feature = "mio-08")]
+impl mio08::event::Source for UnixSeqpacketListener {
+    fn register(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                  -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
+
+#[cfg(feature = "mio-06")]
+impl Evented for UnixSeqpacket {
+    fn register(&self, poll: &Poll, token: Token, events: Ready, opts: PollOpt) -> io::Result<()> {
+        EventedFd(&self.as_raw_fd()).register(poll, token, events, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        events: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.as_raw_fd()).reregister(poll, token, events, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        EventedFd(&self.as_raw_fd()).deregister(poll)
+    }
+}
+
+#[cfg(feature = "mio-08")]
+impl mio08::event::Source for UnixSeqpacket {
+    fn register(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                  -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
+
+impl AsRawFd for UnixSeqpacketListener {
+    fn as_raw_fd(&self) -> i32 {
+        self.inner.fd()
+    }
+}
+
+impl IntoRawFd for UnixSeqpacketListener {
+    fn into_raw_fd(self) -> i32 {
+        self.inner.into_fd()
+    }
+}
+
+impl FromRawFd for UnixSeqpacketListener {
+    unsafe fn from_raw_fd(fd: i32) -> UnixSeqpacketListener {
+        UnixSeqpacketListener {
+            inner: Socket::from_raw_fd(fd),
+            accepted: BufferSizes::default(),
+        }
+    }
+}
+
+impl AsRawFd for UnixSeqpacket {
+    fn as_raw_fd(&self) -> i32 {
+        self.inner.fd()
+    }
+}
+
+impl IntoRawFd for UnixSeqpacket {
+    fn into_raw_fd(self) -> i32 {
+        self.inner.into_fd()
+    }
+}
+
+impl FromRawFd for UnixSeqpacket {
+    unsafe fn from_raw_fd(fd: i32) -> UnixSeqpacket {
+        UnixSeqpacket {
+            inner: Socket::from_raw_fd(fd),
+        }
+    }
+}
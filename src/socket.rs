@@ -1,12 +1,19 @@
-use std::cmp::Ordering;
-use std::io;
+use std::cmp::{self, Ordering};
+use std::ffi::CString;
+use std::io::{self, IoSlice, IoSliceMut};
 use std::mem;
+use std::net::Shutdown;
 use std::os::unix::prelude::*;
 use std::path::Path;
+use std::slice;
+use std::time::{Duration, Instant};
 
 use libc::{self, c_int, c_ulong};
 
+use addr::UnixSocketAddr;
+use ancillary::{AncillaryData, SocketAncillary};
 use cvt;
+use error::{self, SocketError};
 
 // See below for the usage of SOCK_CLOEXEC, but this constant is only defined on
 // Linux currently (e.g. support doesn't exist on other platforms). In order to
@@ -35,6 +42,36 @@ const SOCK_CLOEXEC: c_int = 0;
 )))]
 const SOCK_NONBLOCK: c_int = 0;
 
+// Similarly to SOCK_CLOEXEC above, file descriptors received through
+// SCM_RIGHTS can only be atomically marked as CLOEXEC on some platforms, and
+// elsewhere we fall back to setting the flag after the fact.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+))]
+use libc::MSG_CMSG_CLOEXEC;
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+const MSG_CMSG_CLOEXEC: c_int = 0;
+
+// Rust ignores SIGPIPE by default, but it's still polite to avoid raising it
+// in the first place where the platform lets us.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use libc::MSG_NOSIGNAL;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub const MSG_NOSIGNAL: c_int = 0;
+
+#[derive(Debug)]
 pub struct Socket {
     fd: c_int,
 }
@@ -57,11 +94,13 @@ impl Socket {
                 match cvt(libc::socket(libc::AF_UNIX, flags, 0)) {
                     Ok(fd) => return Ok(Socket { fd: fd }),
                     Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) => {}
-                    Err(e) => return Err(e),
+                    Err(e) => return Err(error::syscall("socket", None, e)),
                 }
             }
 
-            let fd = Socket { fd: try!(cvt(libc::socket(libc::AF_UNIX, ty, 0))) };
+            let fd = cvt(libc::socket(libc::AF_UNIX, ty, 0))
+                .map_err(|e| error::syscall("socket", None, e))?;
+            let fd = Socket { fd: fd };
             try!(cvt(libc::ioctl(fd.fd, libc::FIOCLEX)));
             let mut nonblocking = 1 as c_ulong;
             try!(cvt(libc::ioctl(fd.fd, libc::FIONBIO, &mut nonblocking)));
@@ -86,11 +125,12 @@ impl Socket {
                         return Ok((Socket { fd: fds[0] }, Socket { fd: fds[1] }))
                     }
                     Err(ref e) if e.raw_os_error() == Some(libc::EINVAL) => {},
-                    Err(e) => return Err(e),
+                    Err(e) => return Err(error::syscall("socketpair", None, e)),
                 }
             }
 
-            try!(cvt(libc::socketpair(libc::AF_UNIX, ty, 0, fds.as_mut_ptr())));
+            cvt(libc::socketpair(libc::AF_UNIX, ty, 0, fds.as_mut_ptr()))
+                .map_err(|e| error::syscall("socketpair", None, e))?;
             let a = Socket { fd: fds[0] };
             let b = Socket { fd: fds[1] };
             try!(cvt(libc::ioctl(a.fd, libc::FIOCLEX)));
@@ -102,6 +142,37 @@ impl Socket {
         }
     }
 
+    pub fn accept(&self) -> io::Result<(Socket, UnixSocketAddr)> {
+        accept(self.fd)
+    }
+
+    pub fn duplicate(&self) -> io::Result<Socket> {
+        unsafe {
+            cvt(libc::fcntl(self.fd, libc::F_DUPFD_CLOEXEC, 0)).map(|fd| Socket { fd })
+        }
+    }
+
+    pub fn take_error(&self) -> io::Result<Option<io::Error>> {
+        let err = getsockopt::<c_int>(self.fd, libc::SOL_SOCKET, libc::SO_ERROR)?;
+        if err == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(io::Error::from_raw_os_error(err)))
+        }
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        let how = match how {
+            Shutdown::Read => libc::SHUT_RD,
+            Shutdown::Write => libc::SHUT_WR,
+            Shutdown::Both => libc::SHUT_RDWR,
+        };
+        unsafe {
+            cvt(libc::shutdown(self.fd, how))?;
+        }
+        Ok(())
+    }
+
     pub fn fd(&self) -> c_int {
         self.fd
     }
@@ -113,6 +184,12 @@ impl Socket {
     }
 }
 
+impl FromRawFd for Socket {
+    unsafe fn from_raw_fd(fd: RawFd) -> Socket {
+        Socket { fd }
+    }
+}
+
 impl Drop for Socket {
     fn drop(&mut self) {
         unsafe {
@@ -121,6 +198,33 @@ impl Drop for Socket {
     }
 }
 
+/// Accepts a connection on the listening socket `fd`.
+pub fn accept(fd: c_int) -> io::Result<(Socket, UnixSocketAddr)> {
+    unsafe {
+        let mut addr: libc::sockaddr_un = mem::zeroed();
+        let mut len = mem::size_of_val(&addr) as libc::socklen_t;
+        let storage = &mut addr as *mut _ as *mut _;
+
+        // Like `Socket::new` above, try to atomically set the accepted socket
+        // as CLOEXEC and nonblocking, falling back if accept4 isn't available.
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            let flags = SOCK_CLOEXEC | SOCK_NONBLOCK;
+            match cvt(libc::accept4(fd, storage, &mut len, flags)) {
+                Ok(fd) => return Ok((Socket { fd }, UnixSocketAddr::from_raw(&addr, len))),
+                Err(ref e) if e.raw_os_error() == Some(libc::ENOSYS) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let socket = Socket { fd: cvt(libc::accept(fd, storage, &mut len))? };
+        cvt(libc::ioctl(socket.fd, libc::FIOCLEX))?;
+        let mut nonblocking = 1 as c_ulong;
+        cvt(libc::ioctl(socket.fd, libc::FIONBIO, &mut nonblocking))?;
+        Ok((socket, UnixSocketAddr::from_raw(&addr, len)))
+    }
+}
+
 pub unsafe fn sockaddr_un(path: &Path)
                           -> io::Result<(libc::sockaddr_un, libc::socklen_t)> {
     let mut addr: libc::sockaddr_un = mem::zeroed();
@@ -131,12 +235,22 @@ pub unsafe fn sockaddr_un(path: &Path)
     match (bytes.get(0), bytes.len().cmp(&addr.sun_path.len())) {
         // Abstract paths don't need a null terminator
         (Some(&0), Ordering::Greater) => {
-            return Err(io::Error::new(io::ErrorKind::InvalidInput,
-                                      "path must be no longer than SUN_LEN"));
+            return Err(SocketError::PathTooLong {
+                path: path.to_path_buf(),
+                len: bytes.len(),
+                max: addr.sun_path.len(),
+            }.into());
         }
+        (Some(&0), _) => {}
         (_, Ordering::Greater) | (_, Ordering::Equal) => {
-            return Err(io::Error::new(io::ErrorKind::InvalidInput,
-                                      "path must be shorter than SUN_LEN"));
+            return Err(SocketError::PathTooLong {
+                path: path.to_path_buf(),
+                len: bytes.len(),
+                max: addr.sun_path.len() - 1,
+            }.into());
+        }
+        _ if bytes.contains(&0) => {
+            return Err(SocketError::InteriorNul { path: path.to_path_buf() }.into());
         }
         _ => {}
     }
@@ -154,7 +268,195 @@ pub unsafe fn sockaddr_un(path: &Path)
     Ok((addr, len as libc::socklen_t))
 }
 
-fn sun_path_offset() -> usize {
+/// Starts connecting the socket `fd` to `addr`.
+///
+/// Succeeds if the connection was made or is in progress. A full backlog,
+/// which Linux reports as `EAGAIN` without starting the connection, is
+/// returned as an error.
+pub fn connect(fd: c_int, addr: &libc::sockaddr_un, len: libc::socklen_t) -> io::Result<()> {
+    match cvt(unsafe { libc::connect(fd, addr as *const _ as *const _, len) }) {
+        Ok(_) => Ok(()),
+        Err(ref e) if e.raw_os_error() == Some(libc::EINPROGRESS) => Ok(()),
+        Err(e) => Err(error::syscall("connect", err_addr(addr, len), e)),
+    }
+}
+
+/// Returns whether `err`, returned by `connect`, means that the listener's
+/// backlog is full.
+pub fn is_backlog_full(err: &io::Error) -> bool {
+    SocketError::from_io(err).and_then(SocketError::raw_os_error) == Some(libc::EAGAIN)
+}
+
+/// Waits for up to `timeout`, or forever if `None`, for the socket `fd` to
+/// be ready for `events`.
+///
+/// Returns an error of kind `TimedOut` if it doesn't become ready in time.
+/// Errors and hangups on the socket count as being ready, so that they're
+/// reported by the next operation on it.
+pub fn wait(fd: c_int, events: libc::c_short, timeout: Option<Duration>) -> io::Result<()> {
+    // A timeout too long to represent is as good as none.
+    let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+    let mut pollfd = libc::pollfd { fd, events, revents: 0 };
+    loop {
+        let ms = match deadline {
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                // Round up, so that a short timeout doesn't become a busy loop.
+                let mut ms = left.as_millis();
+                if left.subsec_nanos() % 1_000_000 != 0 {
+                    ms += 1;
+                }
+                cmp::min(ms, c_int::MAX as u128) as c_int
+            }
+            None => -1,
+        };
+        match cvt(unsafe { libc::poll(&mut pollfd, 1, ms) }) {
+            Ok(0) if ms == 0 => {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out waiting on socket"))
+            }
+            Ok(0) => {}
+            Ok(_) => return Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// The directory through which `with_sockaddr_un_at` forms addresses.
+const DIR_FD_PATH: &str = "/proc/self/fd";
+
+/// Returns whether `path` was formed by `with_sockaddr_un_at`, in which case
+/// it no longer leads to the socket file once the directory is closed.
+pub fn is_dir_fd_path(path: &Path) -> bool {
+    path.starts_with(DIR_FD_PATH)
+}
+
+/// Calls `f` with the raw form of `path`.
+///
+/// On Linux, pathnames too long to fit in a `sockaddr_un` are handled by
+/// opening their parent directory and going through `/proc/self/fd` with
+/// `with_sockaddr_un_at` instead.
+pub fn with_sockaddr_un<T, F>(path: &Path, f: F) -> io::Result<T>
+    where F: FnOnce(&libc::sockaddr_un, libc::socklen_t) -> io::Result<T>
+{
+    let (addr, len, _dir) = sockaddr_un_holding_dir(path)?;
+    f(&addr, len)
+}
+
+/// Returns the raw form of `path`, as `with_sockaddr_un` forms it, along
+/// with the directory the address goes through if it was too long to fit.
+///
+/// The address is only valid while that directory stays open.
+pub fn sockaddr_un_holding_dir(path: &Path)
+    -> io::Result<(libc::sockaddr_un, libc::socklen_t, Option<OwnedFd>)>
+{
+    if let Some((dir, name)) = long_path_parts(path) {
+        let dir = open_dir(dir)?;
+        let (addr, len) = match with_sockaddr_un_at(dir.as_raw_fd(), name, |addr, len| {
+            Ok((*addr, len))
+        }) {
+            Ok(addr) => addr,
+            // Report the path the caller actually asked for.
+            Err(ref e) if is_too_long(e) => return Err(too_long(path)),
+            Err(e) => return Err(e),
+        };
+        return Ok((addr, len, Some(dir)))
+    }
+    let (addr, len) = unsafe { sockaddr_un(path)? };
+    Ok((addr, len, None))
+}
+
+/// Checks that `path` isn't too long to form an address with
+/// `sockaddr_un_holding_dir`, without opening its directory.
+///
+/// Only the file name of a path which is too long to fit is checked, as the
+/// length of the path through its directory isn't known until it's opened.
+pub fn check_pathname_len(path: &Path) -> io::Result<()> {
+    let checked = match long_path_parts(path) {
+        Some((_, name)) => name,
+        None => path,
+    };
+    match unsafe { sockaddr_un(checked) } {
+        Ok(_) => Ok(()),
+        Err(ref e) if is_too_long(e) => Err(too_long(path)),
+        Err(e) => Err(e),
+    }
+}
+
+fn is_too_long(err: &io::Error) -> bool {
+    matches!(SocketError::from_io(err), Some(&SocketError::PathTooLong { .. }))
+}
+
+fn too_long(path: &Path) -> io::Error {
+    let max = unsafe { mem::zeroed::<libc::sockaddr_un>() }.sun_path.len() - 1;
+    SocketError::PathTooLong {
+        path: path.to_path_buf(),
+        len: path.as_os_str().len(),
+        max,
+    }.into()
+}
+
+/// Returns the address `addr` refers to, for use in error messages.
+pub fn err_addr(addr: &libc::sockaddr_un, len: libc::socklen_t) -> Option<UnixSocketAddr> {
+    Some(UnixSocketAddr::from_raw(addr, len))
+}
+
+/// Splits a pathname which is too long to fit in a `sockaddr_un` into its
+/// parent directory and file name.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn long_path_parts(path: &Path) -> Option<(&Path, &Path)> {
+    let bytes = path.as_os_str().as_bytes();
+    let max = unsafe { mem::zeroed::<libc::sockaddr_un>() }.sun_path.len();
+    if bytes.len() < max || bytes.first() == Some(&0) {
+        return None
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) if !dir.as_os_str().is_empty() => Some((dir, Path::new(name))),
+        _ => None,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn long_path_parts(_path: &Path) -> Option<(&Path, &Path)> {
+    None
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn open_dir(path: &Path) -> io::Result<OwnedFd> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    unsafe {
+        let flags = libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC;
+        let fd = cvt(libc::open(path.as_ptr(), flags))?;
+        Ok(OwnedFd::from_raw_fd(fd))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn open_dir(_path: &Path) -> io::Result<OwnedFd> {
+    unreachable!()
+}
+
+/// Calls `f` with the raw form of the path `name` relative to the directory
+/// `dir`, which goes through `/proc/self/fd`.
+///
+/// The resulting address is only valid while `dir` stays open.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn with_sockaddr_un_at<T, F>(dir: c_int, name: &Path, f: F) -> io::Result<T>
+    where F: FnOnce(&libc::sockaddr_un, libc::socklen_t) -> io::Result<T>
+{
+    let path = Path::new(DIR_FD_PATH).join(dir.to_string()).join(name);
+    let (addr, len) = unsafe { sockaddr_un(&path)? };
+    f(&addr, len)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn with_sockaddr_un_at<T, F>(_dir: c_int, _name: &Path, _f: F) -> io::Result<T>
+    where F: FnOnce(&libc::sockaddr_un, libc::socklen_t) -> io::Result<T>
+{
+    unreachable!()
+}
+
+pub fn sun_path_offset() -> usize {
     unsafe {
         // Work with an actual instance of the type since using a null pointer is UB
         let addr: libc::sockaddr_un = mem::uninitialized();
@@ -164,3 +466,278 @@ fn sun_path_offset() -> usize {
     }
 }
 
+/// Sets the socket option `name` at `level` on the socket `fd` to `value`.
+pub fn setsockopt<T>(fd: c_int, level: c_int, name: c_int, value: T) -> io::Result<()> {
+    unsafe {
+        cvt(libc::setsockopt(fd,
+                             level,
+                             name,
+                             &value as *const T as *const _,
+                             mem::size_of::<T>() as libc::socklen_t))?;
+        Ok(())
+    }
+}
+
+/// Returns the value of the socket option `name` at `level` on the socket
+/// `fd`.
+pub fn getsockopt<T: Copy>(fd: c_int, level: c_int, name: c_int) -> io::Result<T> {
+    unsafe {
+        let mut value: T = mem::zeroed();
+        let mut len = mem::size_of::<T>() as libc::socklen_t;
+        cvt(libc::getsockopt(fd,
+                             level,
+                             name,
+                             &mut value as *mut T as *mut _,
+                             &mut len))?;
+        Ok(value)
+    }
+}
+
+/// Receives data on the socket `fd` with the `MSG_*` flags in `flags`,
+/// filling in `addr` with the address of the sender.
+///
+/// Returns the number of bytes received, the flags of the received message
+/// (`msg_flags`), and the length of the address.
+pub fn recv_flags(fd: c_int,
+                  buf: &mut [u8],
+                  flags: c_int,
+                  addr: &mut libc::sockaddr_un)
+                  -> io::Result<(usize, c_int, libc::socklen_t)> {
+    unsafe {
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut _,
+            iov_len: buf.len(),
+        };
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_name = addr as *mut _ as *mut _;
+        msg.msg_namelen = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+
+        let rc = libc::recvmsg(fd, &mut msg, flags);
+        if rc < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        // With MSG_TRUNC, Linux returns the full length of the datagram even
+        // if it didn't fit in `buf`. That's still reported by `msg_flags`.
+        Ok((cmp::min(rc as usize, buf.len()), msg.msg_flags, msg.msg_namelen))
+    }
+}
+
+/// Returns `bufs` as a slice of `iovec`s, which `IoSlice` is guaranteed to be
+/// compatible with.
+pub fn iovecs<'a>(bufs: &'a [IoSlice]) -> &'a [libc::iovec] {
+    unsafe { slice::from_raw_parts(bufs.as_ptr() as *const libc::iovec, bufs.len()) }
+}
+
+/// Returns `bufs` as a slice of `iovec`s, which `IoSliceMut` is guaranteed to
+/// be compatible with.
+pub fn iovecs_mut<'a>(bufs: &'a mut [IoSliceMut]) -> &'a [libc::iovec] {
+    unsafe { slice::from_raw_parts(bufs.as_ptr() as *const libc::iovec, bufs.len()) }
+}
+
+/// Reads from `fd` into the buffers in `iov`, filling them in sequentially.
+pub fn readv(fd: c_int, iov: &[libc::iovec]) -> io::Result<usize> {
+    let len = cmp::min(<c_int>::max_value() as usize, iov.len());
+    let rc = unsafe { libc::readv(fd, iov.as_ptr(), len as c_int) };
+    if rc < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(rc as usize)
+    }
+}
+
+/// Writes the buffers in `iov` to `fd` sequentially.
+pub fn writev(fd: c_int, iov: &[libc::iovec]) -> io::Result<usize> {
+    let len = cmp::min(<c_int>::max_value() as usize, iov.len());
+    let rc = unsafe { libc::writev(fd, iov.as_ptr(), len as c_int) };
+    if rc < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(rc as usize)
+    }
+}
+
+/// Sends the buffers in `iov` on the socket `fd` as a single message.
+///
+/// If `addr` is given the message is sent to that address, otherwise it's
+/// sent to the socket's peer.
+pub fn send_iov(fd: c_int,
+                iov: &[libc::iovec],
+                addr: Option<(&libc::sockaddr_un, libc::socklen_t)>)
+                -> io::Result<usize> {
+    unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = iov.as_ptr() as *mut _;
+        msg.msg_iovlen = iov.len() as _;
+        if let Some((addr, len)) = addr {
+            msg.msg_name = addr as *const _ as *mut _;
+            msg.msg_namelen = len;
+        }
+
+        let rc = libc::sendmsg(fd, &msg, MSG_NOSIGNAL);
+        if rc < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(rc as usize)
+        }
+    }
+}
+
+/// Receives a single message from the socket `fd` into the buffers in
+/// `iov`, filling in `addr` with the address of the sender.
+///
+/// Returns the number of bytes received and the length of the address.
+pub fn recv_iov(fd: c_int,
+                iov: &[libc::iovec],
+                addr: &mut libc::sockaddr_un)
+                -> io::Result<(usize, libc::socklen_t)> {
+    unsafe {
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = iov.as_ptr() as *mut _;
+        msg.msg_iovlen = iov.len() as _;
+        msg.msg_name = addr as *mut _ as *mut _;
+        msg.msg_namelen = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+
+        let rc = libc::recvmsg(fd, &mut msg, 0);
+        if rc < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        Ok((rc as usize, msg.msg_namelen))
+    }
+}
+
+/// Sends data on the socket `fd` with the `MSG_*` flags in `flags`.
+pub fn send_flags(fd: c_int, buf: &[u8], flags: c_int) -> io::Result<usize> {
+    unsafe {
+        let rc = libc::send(fd, buf.as_ptr() as *const _, buf.len(), flags);
+        if rc < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(rc as usize)
+        }
+    }
+}
+
+/// Sets the `SOL_SOCKET` option `name`, whose value is a size, on the socket
+/// `fd`.
+pub fn set_size_opt(fd: c_int, name: c_int, size: usize) -> io::Result<()> {
+    let size = cmp::min(size, c_int::MAX as usize) as c_int;
+    setsockopt(fd, libc::SOL_SOCKET, name, size)
+}
+
+/// Returns the `SOL_SOCKET` option `name`, whose value is a size, of the
+/// socket `fd`.
+pub fn size_opt(fd: c_int, name: c_int) -> io::Result<usize> {
+    getsockopt::<c_int>(fd, libc::SOL_SOCKET, name).map(|size| size as usize)
+}
+
+/// The sizes of the send and receive buffers to give a socket, if any.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BufferSizes {
+    pub send: Option<usize>,
+    pub recv: Option<usize>,
+}
+
+impl BufferSizes {
+    /// Sets `SO_SNDBUF` and `SO_RCVBUF` on the socket `fd`.
+    pub fn apply(&self, fd: c_int) -> io::Result<()> {
+        if let Some(size) = self.send {
+            set_size_opt(fd, libc::SO_SNDBUF, size)?;
+        }
+        if let Some(size) = self.recv {
+            set_size_opt(fd, libc::SO_RCVBUF, size)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sends `buf` on the socket `fd` along with the control messages in
+/// `ancillary`.
+///
+/// If `addr` is given the data is sent to that address, otherwise it's sent
+/// to the socket's peer.
+pub fn send_msg(fd: c_int,
+                buf: &[u8],
+                ancillary: &SocketAncillary,
+                addr: Option<(&libc::sockaddr_un, libc::socklen_t)>)
+                -> io::Result<usize> {
+    unsafe {
+        let mut iov = libc::iovec {
+            iov_base: buf.as_ptr() as *mut _,
+            iov_len: buf.len(),
+        };
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if let Some((addr, len)) = addr {
+            msg.msg_name = addr as *const _ as *mut _;
+            msg.msg_namelen = len;
+        }
+        let (control, len) = ancillary.as_control();
+        msg.msg_control = control;
+        msg.msg_controllen = len as _;
+
+        let rc = libc::sendmsg(fd, &msg, MSG_NOSIGNAL);
+        if rc < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(rc as usize)
+        }
+    }
+}
+
+/// Receives data from the socket `fd` into `buf` and control messages into
+/// `ancillary`.
+///
+/// The address of the sender is written to `addr`, and the number of bytes
+/// read and the length of the address are returned. Any file descriptors
+/// received are marked as close-on-exec.
+pub fn recv_msg(fd: c_int,
+                buf: &mut [u8],
+                ancillary: &mut SocketAncillary,
+                addr: &mut libc::sockaddr_un)
+                -> io::Result<(usize, libc::socklen_t)> {
+    unsafe {
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut _,
+            iov_len: buf.len(),
+        };
+        let mut msg: libc::msghdr = mem::zeroed();
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_name = addr as *mut _ as *mut _;
+        msg.msg_namelen = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+        let (control, len) = ancillary.as_control_mut();
+        msg.msg_control = control;
+        msg.msg_controllen = len as _;
+
+        let rc = libc::recvmsg(fd, &mut msg, MSG_CMSG_CLOEXEC);
+        if rc < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        ancillary.set_received(msg.msg_controllen as usize,
+                               msg.msg_flags & libc::MSG_CTRUNC != 0);
+
+        if MSG_CMSG_CLOEXEC == 0 {
+            let mut res = Ok(0);
+            for message in ancillary.messages() {
+                if let AncillaryData::ScmRights(rights) = message {
+                    for fd in rights {
+                        if res.is_ok() {
+                            res = cvt(libc::ioctl(fd, libc::FIOCLEX));
+                        }
+                    }
+                }
+            }
+            if let Err(e) = res {
+                // None of the file descriptors are handed out, so close them
+                // all rather than leaking them.
+                ancillary.take_fds(&mut Vec::new(), 0);
+                return Err(e)
+            }
+        }
+
+        Ok((rc as usize, msg.msg_namelen))
+    }
+}
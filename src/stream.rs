@@ -1,20 +1,35 @@
-use std::cmp;
 use std::io::prelude::*;
-use std::io;
+use std::io::{self, IoSlice, IoSliceMut};
+use std::mem;
 use std::os::unix::net;
 use std::os::unix::prelude::*;
 use std::path::Path;
+use std::time::Duration;
 use std::net::Shutdown;
 
+#[cfg(feature = "iovec")]
 use iovec::IoVec;
+#[cfg(feature = "iovec")]
 use iovec::unix as iovec;
 use libc;
+#[cfg(feature = "mio-06")]
 use mio::event::Evented;
+#[cfg(feature = "mio-06")]
 use mio::unix::EventedFd;
+#[cfg(feature = "mio-06")]
 use mio::{Poll, Token, Ready, PollOpt};
+#[cfg(feature = "mio-08")]
+use mio08::{self, Interest, Registry};
+#[cfg(feature = "mio-08")]
+use mio08::unix::SourceFd;
 
+use addr::{self, UnixSocketAddr};
 use cvt;
-use socket::{sockaddr_un, Socket};
+use ancillary::SocketAncillary;
+use connect;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use ucred::{self, UCred};
+use socket::{self, Socket};
 
 /// A Unix stream socket.
 ///
@@ -28,6 +43,9 @@ use socket::{sockaddr_un, Socket};
 ///
 /// A `UnixStream` implements the `Read`, `Write`, `Evented`, `AsRawFd`,
 /// `IntoRawFd`, and `FromRawFd` traits for interoperating with other I/O code.
+/// `Evented` is mio 0.6's trait and requires the `mio-06` feature, which is
+/// enabled by default. With the `mio-08` feature mio 0.8's `event::Source` is
+/// implemented as well.
 ///
 /// Note that all values of this type are typically in nonblocking mode, so the
 /// `read` and `write` methods may return an error with the kind of
@@ -43,23 +61,80 @@ impl UnixStream {
     /// The socket returned may not be readable and/or writable yet, as the
     /// connection may be in progress. The socket should be registered with an
     /// event loop to wait on both of these properties being available.
+    ///
+    /// If the listener's backlog is full the connection isn't made, and an
+    /// error of kind `WouldBlock` wrapping the `EAGAIN` from `connect` is
+    /// returned. `PendingConnect` can be used to wait for room instead.
     pub fn connect<P: AsRef<Path>>(p: P) -> io::Result<UnixStream> {
         UnixStream::_connect(p.as_ref())
     }
 
     fn _connect(path: &Path) -> io::Result<UnixStream> {
-        unsafe {
-            let (addr, len) = try!(sockaddr_un(path));
-            let socket = try!(Socket::new(libc::SOCK_STREAM));
-            let addr = &addr as *const _ as *const _;
-            match cvt(libc::connect(socket.fd(), addr, len)) {
-                Ok(_) => {}
-                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
-                Err(e) => return Err(e),
-            }
-
-            Ok(UnixStream::from_raw_fd(socket.into_fd()))
-        }
+        socket::with_sockaddr_un(path, UnixStream::connect_raw)
+    }
+
+    /// Connects to the socket named `name` in the directory `dir`.
+    ///
+    /// This makes it possible to connect to paths which are too long to fit
+    /// in a socket address, which `connect` also falls back to
+    /// automatically.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn connect_at<D: AsRawFd, P: AsRef<Path>>(dir: &D, name: P) -> io::Result<UnixStream> {
+        socket::with_sockaddr_un_at(dir.as_raw_fd(), name.as_ref(), UnixStream::connect_raw)
+    }
+
+    /// Connects to the socket named by `path`, waiting for up to `timeout`
+    /// for the connection to be made.
+    ///
+    /// Unlike `connect`, the connection has been fully established when this
+    /// returns, waiting for room if the listener's backlog is full. An error
+    /// of kind `TimedOut` is returned if that takes too long. The returned
+    /// stream is in nonblocking mode as usual.
+    pub fn connect_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> io::Result<UnixStream> {
+        socket::with_sockaddr_un(path.as_ref(), |addr, len| {
+            let socket = connect::connect_timeout(addr, len, timeout)?;
+            Ok(unsafe { UnixStream::from_raw_fd(socket.into_fd()) })
+        })
+    }
+
+    /// Connects to the socket at the specified address.
+    ///
+    /// This is the same as `connect`, except that abstract addresses can be
+    /// connected to directly rather than through paths starting with a nul
+    /// byte.
+    pub fn connect_addr(addr: &UnixSocketAddr) -> io::Result<UnixStream> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        UnixStream::connect_raw(&addr, len)
+    }
+
+    fn connect_raw(addr: &libc::sockaddr_un, len: libc::socklen_t) -> io::Result<UnixStream> {
+        let socket = Socket::new(libc::SOCK_STREAM)?;
+        UnixStream::connect_socket(socket, addr, len)
+    }
+
+    fn connect_socket(socket: Socket,
+                      addr: &libc::sockaddr_un,
+                      len: libc::socklen_t) -> io::Result<UnixStream> {
+        socket::connect(socket.fd(), addr, len)?;
+        Ok(unsafe { UnixStream::from_raw_fd(socket.into_fd()) })
+    }
+
+    /// Connects to the socket at the specified address from a local address
+    /// in the abstract namespace which is picked by the kernel.
+    ///
+    /// This gives the server a distinct address to identify the client by,
+    /// without creating a file which needs to be cleaned up afterwards. The
+    /// assigned local address is returned alongside the stream.
+    ///
+    /// As with `connect`, the connection may still be in progress when this
+    /// returns.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn autobind_connect(addr: &UnixSocketAddr) -> io::Result<(UnixStream, UnixSocketAddr)> {
+        let (addr, len, _dir) = addr.to_raw()?;
+        let socket = Socket::new(libc::SOCK_STREAM)?;
+        let local = addr::autobind(socket.fd())?;
+        let stream = UnixStream::connect_socket(socket, &addr, len)?;
+        Ok((stream, local))
     }
 
     /// Consumes a standard library `UnixStream` and returns a wrapped
@@ -99,16 +174,183 @@ impl UnixStream {
         self.inner.local_addr()
     }
 
+    /// Returns the socket address of the local half of this connection as a
+    /// `UnixSocketAddr`.
+    pub fn local_unix_addr(&self) -> io::Result<UnixSocketAddr> {
+        addr::local_addr(self.inner.as_raw_fd())
+    }
+
     /// Returns the socket address of the remote half of this connection.
     pub fn peer_addr(&self) -> io::Result<net::SocketAddr> {
         self.inner.peer_addr()
     }
 
+    /// Returns the socket address of the remote half of this connection as a
+    /// `UnixSocketAddr`.
+    pub fn peer_unix_addr(&self) -> io::Result<UnixSocketAddr> {
+        addr::peer_addr(self.inner.as_raw_fd())
+    }
+
+    /// Returns the credentials of the process on the other end of this
+    /// connection.
+    ///
+    /// These are the credentials the peer had when it called `connect`, or
+    /// when the pair was created, as reported by the `SO_PEERCRED` option.
+    /// They're available as soon as the connection is established, so
+    /// servers can use them to authorize a client before reading any data.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn peer_cred(&self) -> io::Result<UCred> {
+        ucred::peer_cred(self.inner.as_raw_fd())
+    }
+
     /// Returns the value of the `SO_ERROR` option.
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
         self.inner.take_error()
     }
 
+    /// Blocks until this socket is readable, or until `timeout` has passed
+    /// if it's not `None`.
+    ///
+    /// This is useful for simple programs which don't otherwise need an
+    /// event loop. An error of kind `TimedOut` is returned if the timeout
+    /// passes first.
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        socket::wait(self.as_raw_fd(), libc::POLLIN, timeout)
+    }
+
+    /// Blocks until this socket is writable, or until `timeout` has passed
+    /// if it's not `None`.
+    ///
+    /// An error of kind `TimedOut` is returned if the timeout passes first.
+    pub fn wait_writable(&self, timeout: Option<Duration>) -> io::Result<()> {
+        socket::wait(self.as_raw_fd(), libc::POLLOUT, timeout)
+    }
+
+    /// Receives data from the socket without removing it from the queue.
+    ///
+    /// Successive calls return the same data, until it's consumed by a
+    /// `read`. This allows inspecting the start of a stream, for example to
+    /// decide how to handle it, before reading any of it.
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv_with_flags(buf, libc::MSG_PEEK).map(|(n, _)| n)
+    }
+
+    /// Receives data from the socket with the `MSG_*` flags in `flags`, such
+    /// as `libc::MSG_PEEK` or `libc::MSG_WAITALL`.
+    ///
+    /// On success, returns the number of bytes read and the flags describing
+    /// the received data (`msg_flags`).
+    pub fn recv_with_flags(&self, buf: &mut [u8], flags: libc::c_int)
+                           -> io::Result<(usize, libc::c_int)> {
+        let mut addr = unsafe { mem::zeroed() };
+        socket::recv_flags(self.as_raw_fd(), buf, flags, &mut addr).map(|(n, flags, _)| (n, flags))
+    }
+
+    /// Sends data on the socket with the `MSG_*` flags in `flags`, such as
+    /// `libc::MSG_NOSIGNAL` or `libc::MSG_DONTWAIT`.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_with_flags(&self, buf: &[u8], flags: libc::c_int) -> io::Result<usize> {
+        socket::send_flags(self.as_raw_fd(), buf, flags)
+    }
+
+    /// Returns the number of bytes which can be read without blocking
+    /// (`FIONREAD`).
+    pub fn bytes_available(&self) -> io::Result<usize> {
+        let mut n: libc::c_int = 0;
+        unsafe {
+            cvt(libc::ioctl(self.as_raw_fd(), libc::FIONREAD, &mut n))?;
+        }
+        Ok(n as usize)
+    }
+
+    /// Returns the amount of data written to this socket which the peer
+    /// hasn't read yet (`SIOCOUTQ`).
+    ///
+    /// For Unix sockets Linux counts the memory used to queue the data,
+    /// including bookkeeping overhead, so this is an upper bound on the
+    /// number of unread bytes. It is zero once the peer has read everything.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn unsent_bytes(&self) -> io::Result<usize> {
+        let mut n: libc::c_int = 0;
+        unsafe {
+            // SIOCOUTQ has the same value, but libc only exposes it by its
+            // terminal name.
+            cvt(libc::ioctl(self.as_raw_fd(), libc::TIOCOUTQ, &mut n))?;
+        }
+        Ok(n as usize)
+    }
+
+    /// Sets the size of the send buffer (`SO_SNDBUF`).
+    ///
+    /// The kernel caps this, on Linux to `net.core.wmem_max`, and may double
+    /// the value to leave room for bookkeeping.
+    pub fn set_send_buffer_size(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_SNDBUF, size)
+    }
+
+    /// Returns the size of the send buffer (`SO_SNDBUF`).
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_SNDBUF)
+    }
+
+    /// Sets the size of the receive buffer (`SO_RCVBUF`).
+    ///
+    /// The kernel caps this, on Linux to `net.core.rmem_max`, and may double
+    /// the value to leave room for bookkeeping.
+    pub fn set_recv_buffer_size(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_RCVBUF, size)
+    }
+
+    /// Returns the size of the receive buffer (`SO_RCVBUF`).
+    pub fn recv_buffer_size(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_RCVBUF)
+    }
+
+    /// Sets the size of the send buffer ignoring the system-wide limit
+    /// (`SO_SNDBUFFORCE`).
+    ///
+    /// This requires the `CAP_NET_ADMIN` capability.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_send_buffer_size_force(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_SNDBUFFORCE, size)
+    }
+
+    /// Sets the size of the receive buffer ignoring the system-wide limit
+    /// (`SO_RCVBUFFORCE`).
+    ///
+    /// This requires the `CAP_NET_ADMIN` capability.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_recv_buffer_size_force(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_RCVBUFFORCE, size)
+    }
+
+    /// Sets the minimum number of bytes to process for receive operations
+    /// (`SO_RCVLOWAT`).
+    pub fn set_recv_low_water(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_RCVLOWAT, size)
+    }
+
+    /// Returns the minimum number of bytes to process for receive operations
+    /// (`SO_RCVLOWAT`).
+    pub fn recv_low_water(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_RCVLOWAT)
+    }
+
+    /// Sets the minimum number of bytes to process for send operations
+    /// (`SO_SNDLOWAT`).
+    ///
+    /// Linux doesn't allow this to be changed, and returns an error.
+    pub fn set_send_low_water(&self, size: usize) -> io::Result<()> {
+        socket::set_size_opt(self.as_raw_fd(), libc::SO_SNDLOWAT, size)
+    }
+
+    /// Returns the minimum number of bytes to process for send operations
+    /// (`SO_SNDLOWAT`).
+    pub fn send_low_water(&self) -> io::Result<usize> {
+        socket::size_opt(self.as_raw_fd(), libc::SO_SNDLOWAT)
+    }
+
     /// Shuts down the read, write, or both halves of this connection.
     ///
     /// This function will cause all pending and future I/O calls on the
@@ -118,6 +360,30 @@ impl UnixStream {
         self.inner.shutdown(how)
     }
 
+    /// Reads from the socket into a list of buffers all at once.
+    ///
+    /// The buffers are filled in sequentially, a buffer being entirely
+    /// filled up before the next is written to. This is the same as
+    /// `Read::read_vectored`, but only needs a shared reference.
+    ///
+    /// On success, returns the number of bytes read. If no bytes are
+    /// available to be read yet then a "would block" error is returned.
+    pub fn read_vectored(&self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        socket::readv(self.inner.as_raw_fd(), socket::iovecs_mut(bufs))
+    }
+
+    /// Writes the contents of a list of buffers to the socket all at once.
+    ///
+    /// The buffers are written sequentially, a buffer being entirely written
+    /// before the next is. This is the same as `Write::write_vectored`, but
+    /// only needs a shared reference.
+    ///
+    /// On success, returns the number of bytes written. If the socket is not
+    /// currently writable then a "would block" error is returned.
+    pub fn write_vectored(&self, bufs: &[IoSlice]) -> io::Result<usize> {
+        socket::writev(self.inner.as_raw_fd(), socket::iovecs(bufs))
+    }
+
     /// Read in a list of buffers all at once.
     ///
     /// This operation will attempt to read bytes from this socket and place
@@ -130,19 +396,12 @@ impl UnixStream {
     /// The number of bytes read is returned, if successful, or an error is
     /// returned otherwise. If no bytes are available to be read yet then
     /// a "would block" error is returned. This operation does not block.
+    ///
+    /// This requires the `iovec` feature, which is enabled by default.
+    /// `read_vectored` does the same with `std::io::IoSliceMut`.
+    #[cfg(feature = "iovec")]
     pub fn read_bufs(&self, bufs: &mut [&mut IoVec]) -> io::Result<usize> {
-        unsafe {
-            let slice = iovec::as_os_slice_mut(bufs);
-            let len = cmp::min(<libc::c_int>::max_value() as usize, slice.len());
-            let rc = libc::readv(self.inner.as_raw_fd(),
-                                slice.as_ptr(),
-                                len as libc::c_int);
-            if rc < 0 {
-                Err(io::Error::last_os_error())
-            } else {
-                Ok(rc as usize)
-            }
-        }
+        socket::readv(self.inner.as_raw_fd(), iovec::as_os_slice_mut(bufs))
     }
 
     /// Write a list of buffers all at once.
@@ -157,22 +416,137 @@ impl UnixStream {
     /// The number of bytes written is returned, if successful, or an error is
     /// returned otherwise. If the socket is not currently writable then a
     /// "would block" error is returned. This operation does not block.
+    ///
+    /// This requires the `iovec` feature, which is enabled by default.
+    /// `write_vectored` does the same with `std::io::IoSlice`.
+    #[cfg(feature = "iovec")]
     pub fn write_bufs(&self, bufs: &[&IoVec]) -> io::Result<usize> {
-        unsafe {
-            let slice = iovec::as_os_slice(bufs);
-            let len = cmp::min(<libc::c_int>::max_value() as usize, slice.len());
-            let rc = libc::writev(self.inner.as_raw_fd(),
-                                 slice.as_ptr(),
-                                 len as libc::c_int);
-            if rc < 0 {
-                Err(io::Error::last_os_error())
-            } else {
-                Ok(rc as usize)
-            }
+        socket::writev(self.inner.as_raw_fd(), iovec::as_os_slice(bufs))
+    }
+
+    /// Sends data along with a list of file descriptors.
+    ///
+    /// The file descriptors in `fds` are passed to the peer as an
+    /// `SCM_RIGHTS` control message attached to the data in `buf`, which
+    /// must not be empty if any file descriptors are being sent. The file
+    /// descriptors remain owned by the caller, and the peer receives
+    /// duplicates of them.
+    ///
+    /// The number of bytes written is returned, if successful, or an error is
+    /// returned otherwise. If the socket is not currently writable then a
+    /// "would block" error is returned. This operation does not block.
+    pub fn send_with_fds(&self, buf: &[u8], fds: &[RawFd]) -> io::Result<usize> {
+        let mut ancillary = SocketAncillary::with_capacity(SocketAncillary::space_for_fds(fds.len()));
+        if !fds.is_empty() {
+            ancillary.add_fds(fds);
         }
+        self.send_with_ancillary(buf, &ancillary)
+    }
+
+    /// Receives data along with any file descriptors sent by the peer.
+    ///
+    /// Up to `max_fds` file descriptors received with the data are pushed
+    /// onto `fds`. They are owned by the caller and have the close-on-exec
+    /// flag set.
+    ///
+    /// On success, returns the number of bytes read and whether the peer sent
+    /// more file descriptors than `max_fds`. In that case the excess file
+    /// descriptors have been closed by the kernel and are lost.
+    ///
+    /// If no bytes are available to be read yet then a "would block" error is
+    /// returned. This operation does not block.
+    pub fn recv_with_fds(&self,
+                         buf: &mut [u8],
+                         fds: &mut Vec<OwnedFd>,
+                         max_fds: usize) -> io::Result<(usize, bool)> {
+        let mut ancillary = SocketAncillary::with_capacity(SocketAncillary::space_for_fds(max_fds));
+        let n = self.recv_with_ancillary(buf, &mut ancillary)?;
+        Ok((n, ancillary.take_fds(fds, max_fds)))
+    }
+
+    /// Sets the value of the `SO_PASSCRED` option for this socket.
+    ///
+    /// When enabled, the credentials of the sending process are attached to
+    /// all data received on this socket and can be retrieved with
+    /// `recv_with_cred`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn set_passcred(&self, passcred: bool) -> io::Result<()> {
+        ucred::set_passcred(self.inner.as_raw_fd(), passcred)
+    }
+
+    /// Returns the value of the `SO_PASSCRED` option for this socket.
+    ///
+    /// For more information about this option, see `set_passcred`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn passcred(&self) -> io::Result<bool> {
+        ucred::passcred(self.inner.as_raw_fd())
+    }
+
+    /// Sends data with explicit credentials attached.
+    ///
+    /// Unless the sending process is privileged, `cred` must match its own
+    /// process, user and group IDs. The peer receives them if it has enabled
+    /// `SO_PASSCRED`.
+    ///
+    /// The number of bytes written is returned, if successful, or an error is
+    /// returned otherwise. If the socket is not currently writable then a
+    /// "would block" error is returned. This operation does not block.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn send_with_cred(&self, buf: &[u8], cred: UCred) -> io::Result<usize> {
+        self.send_with_ancillary(buf, &ucred::send_cred_ancillary(cred))
+    }
+
+    /// Receives data along with the credentials of the process which sent it.
+    ///
+    /// Credentials are only received if `SO_PASSCRED` has been enabled on
+    /// this socket with `set_passcred`, otherwise `None` is returned for them.
+    /// Note that the kernel won't coalesce data sent with differing
+    /// credentials, so the returned credentials apply to all bytes read.
+    /// Any file descriptors sent along with the data are closed.
+    ///
+    /// If no bytes are available to be read yet then a "would block" error is
+    /// returned. This operation does not block.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn recv_with_cred(&self, buf: &mut [u8]) -> io::Result<(usize, Option<UCred>)> {
+        let mut ancillary = ucred::cred_ancillary();
+        let n = self.recv_with_ancillary(buf, &mut ancillary)?;
+        Ok((n, ucred::received_cred(&mut ancillary)))
+    }
+
+    /// Sends data along with the control messages in `ancillary`.
+    ///
+    /// The control messages are attached to the data in `buf`, which must not
+    /// be empty if `ancillary` isn't.
+    ///
+    /// The number of bytes written is returned, if successful, or an error is
+    /// returned otherwise. If the socket is not currently writable then a
+    /// "would block" error is returned. This operation does not block.
+    pub fn send_with_ancillary(&self,
+                               buf: &[u8],
+                               ancillary: &SocketAncillary) -> io::Result<usize> {
+        if buf.is_empty() && !ancillary.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "cannot send control messages without data"))
+        }
+        socket::send_msg(self.inner.as_raw_fd(), buf, ancillary, None)
+    }
+
+    /// Receives data along with any control messages sent by the peer.
+    ///
+    /// The control messages are written to `ancillary`, replacing its previous
+    /// contents, and the number of bytes read is returned.
+    ///
+    /// If no bytes are available to be read yet then a "would block" error is
+    /// returned. This operation does not block.
+    pub fn recv_with_ancillary(&self,
+                               buf: &mut [u8],
+                               ancillary: &mut SocketAncillary) -> io::Result<usize> {
+        let mut addr = unsafe { mem::zeroed() };
+        socket::recv_msg(self.inner.as_raw_fd(), buf, ancillary, &mut addr).map(|p| p.0)
     }
 }
 
+#[cfg(feature = "mio-06")]
 impl Evented for UnixStream {
     fn register(&self,
                 poll: &Poll,
@@ -195,16 +569,41 @@ impl Evented for UnixStream {
     }
 }
 
+#[cfg(feature = "mio-08")]
+impl mio08::event::Source for UnixStream {
+    fn register(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &Registry, token: mio08::Token, interests: Interest)
+                  -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
+
 impl Read for UnixStream {
     fn read(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
         self.inner.read(bytes)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        UnixStream::read_vectored(self, bufs)
+    }
 }
 
 impl<'a> Read for &'a UnixStream {
     fn read(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
         (&self.inner).read(bytes)
     }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        UnixStream::read_vectored(self, bufs)
+    }
 }
 
 impl Write for UnixStream {
@@ -212,6 +611,10 @@ impl Write for UnixStream {
         self.inner.write(bytes)
     }
 
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        UnixStream::write_vectored(self, bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         self.inner.flush()
     }
@@ -222,6 +625,10 @@ impl<'a> Write for &'a UnixStream {
         (&self.inner).write(bytes)
     }
 
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        UnixStream::write_vectored(self, bufs)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         (&self.inner).flush()
     }
@@ -0,0 +1,386 @@
+//! Integration with tokio.
+//!
+//! The types in this module wrap this crate's sockets in tokio's `AsyncFd`,
+//! so they can be used from async code running on a tokio runtime while
+//! keeping the rest of their API available through `get_ref`. They must be
+//! created from within a runtime with I/O enabled.
+//!
+//! This module requires the `tokio` feature. With it the sockets can also be
+//! converted to and from the corresponding `tokio::net` types with
+//! `into_tokio` and `from_tokio`, without reopening them.
+
+use std::future::{self, Future};
+use std::io::{self, IoSlice, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net;
+use std::os::unix::prelude::*;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio1;
+use tokio1::io::unix::AsyncFd;
+use tokio1::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use UnixSocketAddr;
+
+macro_rules! ready {
+    ($e:expr) => (match $e {
+        Poll::Ready(t) => t,
+        Poll::Pending => return Poll::Pending,
+    })
+}
+
+/// Calls `f` once `fd` is readable, until it no longer would block.
+fn poll_read<T, R, F>(fd: &AsyncFd<T>, cx: &mut Context, mut f: F) -> Poll<io::Result<R>>
+    where T: AsRawFd,
+          F: FnMut(&T) -> io::Result<R>,
+{
+    loop {
+        let mut guard = ready!(fd.poll_read_ready(cx))?;
+        if let Ok(res) = guard.try_io(|fd| f(fd.get_ref())) {
+            return Poll::Ready(res)
+        }
+    }
+}
+
+/// Calls `f` once `fd` is writable, until it no longer would block.
+fn poll_write<T, R, F>(fd: &AsyncFd<T>, cx: &mut Context, mut f: F) -> Poll<io::Result<R>>
+    where T: AsRawFd,
+          F: FnMut(&T) -> io::Result<R>,
+{
+    loop {
+        let mut guard = ready!(fd.poll_write_ready(cx))?;
+        if let Ok(res) = guard.try_io(|fd| f(fd.get_ref())) {
+            return Poll::Ready(res)
+        }
+    }
+}
+
+/// A `UnixStream` registered with a tokio runtime.
+///
+/// This implements `AsyncRead` and `AsyncWrite`.
+#[derive(Debug)]
+pub struct UnixStream {
+    inner: AsyncFd<::UnixStream>,
+}
+
+impl UnixStream {
+    /// Registers `stream` with the current tokio runtime.
+    pub fn new(stream: ::UnixStream) -> io::Result<UnixStream> {
+        Ok(UnixStream { inner: AsyncFd::new(stream)? })
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &::UnixStream {
+        self.inner.get_ref()
+    }
+
+    /// Deregisters the stream from the runtime and returns it.
+    pub fn into_inner(self) -> ::UnixStream {
+        self.inner.into_inner()
+    }
+
+    /// Attempts to send data along with a list of file descriptors, as
+    /// `UnixStream::send_with_fds` does.
+    pub fn poll_send_with_fds(&self, cx: &mut Context, buf: &[u8], fds: &[RawFd])
+                              -> Poll<io::Result<usize>> {
+        poll_write(&self.inner, cx, |s| s.send_with_fds(buf, fds))
+    }
+
+    /// Sends data along with a list of file descriptors, as
+    /// `UnixStream::send_with_fds` does.
+    pub fn send_with_fds<'a>(&'a self, buf: &'a [u8], fds: &'a [RawFd])
+                             -> impl Future<Output = io::Result<usize>> + 'a {
+        future::poll_fn(move |cx| self.poll_send_with_fds(cx, buf, fds))
+    }
+
+    /// Attempts to receive data along with any file descriptors sent by the
+    /// peer, as `UnixStream::recv_with_fds` does.
+    pub fn poll_recv_with_fds(&self,
+                              cx: &mut Context,
+                              buf: &mut [u8],
+                              fds: &mut Vec<OwnedFd>,
+                              max_fds: usize) -> Poll<io::Result<(usize, bool)>> {
+        poll_read(&self.inner, cx, |s| s.recv_with_fds(buf, fds, max_fds))
+    }
+
+    /// Receives data along with any file descriptors sent by the peer, as
+    /// `UnixStream::recv_with_fds` does.
+    pub fn recv_with_fds<'a>(&'a self,
+                             buf: &'a mut [u8],
+                             fds: &'a mut Vec<OwnedFd>,
+                             max_fds: usize)
+                             -> impl Future<Output = io::Result<(usize, bool)>> + 'a {
+        future::poll_fn(move |cx| self.poll_recv_with_fds(cx, buf, fds, max_fds))
+    }
+}
+
+impl AsyncRead for UnixStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf)
+                 -> Poll<io::Result<()>> {
+        let n = ready!(poll_read(&self.inner, cx, |mut s| s.read(buf.initialize_unfilled())))?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for UnixStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        poll_write(&self.inner, cx, |mut s| s.write(buf))
+    }
+
+    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context, bufs: &[IoSlice])
+                           -> Poll<io::Result<usize>> {
+        poll_write(&self.inner, cx, |s| s.write_vectored(bufs))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(self.inner.get_ref().shutdown(Shutdown::Write))
+    }
+}
+
+/// A `UnixListener` registered with a tokio runtime.
+#[derive(Debug)]
+pub struct UnixListener {
+    inner: AsyncFd<::UnixListener>,
+}
+
+impl UnixListener {
+    /// Registers `listener` with the current tokio runtime.
+    pub fn new(listener: ::UnixListener) -> io::Result<UnixListener> {
+        Ok(UnixListener { inner: AsyncFd::new(listener)? })
+    }
+
+    /// Returns a reference to the underlying listener.
+    pub fn get_ref(&self) -> &::UnixListener {
+        self.inner.get_ref()
+    }
+
+    /// Deregisters the listener from the runtime and returns it.
+    pub fn into_inner(self) -> ::UnixListener {
+        self.inner.into_inner()
+    }
+
+    /// Attempts to accept a new incoming connection.
+    ///
+    /// The accepted stream is registered with the current tokio runtime.
+    pub fn poll_accept(&self, cx: &mut Context)
+                       -> Poll<io::Result<(UnixStream, net::SocketAddr)>> {
+        let (stream, addr) = ready!(poll_read(&self.inner, cx, |l| {
+            match l.accept()? {
+                Some(accepted) => Ok(accepted),
+                None => Err(io::ErrorKind::WouldBlock.into()),
+            }
+        }))?;
+        Poll::Ready(UnixStream::new(stream).map(|stream| (stream, addr)))
+    }
+
+    /// Accepts a new incoming connection.
+    ///
+    /// The accepted stream is registered with the current tokio runtime.
+    pub fn accept<'a>(&'a self)
+                      -> impl Future<Output = io::Result<(UnixStream, net::SocketAddr)>> + 'a {
+        future::poll_fn(move |cx| self.poll_accept(cx))
+    }
+}
+
+/// A `UnixDatagram` registered with a tokio runtime.
+#[derive(Debug)]
+pub struct UnixDatagram {
+    inner: AsyncFd<::UnixDatagram>,
+}
+
+impl UnixDatagram {
+    /// Registers `socket` with the current tokio runtime.
+    pub fn new(socket: ::UnixDatagram) -> io::Result<UnixDatagram> {
+        Ok(UnixDatagram { inner: AsyncFd::new(socket)? })
+    }
+
+    /// Returns a reference to the underlying socket.
+    pub fn get_ref(&self) -> &::UnixDatagram {
+        self.inner.get_ref()
+    }
+
+    /// Deregisters the socket from the runtime and returns it.
+    pub fn into_inner(self) -> ::UnixDatagram {
+        self.inner.into_inner()
+    }
+
+    /// Attempts to receive data from the socket.
+    pub fn poll_recv(&self, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        poll_read(&self.inner, cx, |s| s.recv(buf))
+    }
+
+    /// Receives data from the socket.
+    ///
+    /// On success, returns the number of bytes read.
+    pub fn recv<'a>(&'a self, buf: &'a mut [u8])
+                    -> impl Future<Output = io::Result<usize>> + 'a {
+        future::poll_fn(move |cx| self.poll_recv(cx, buf))
+    }
+
+    /// Attempts to receive data from the socket, along with the address of
+    /// the sender.
+    pub fn poll_recv_from(&self, cx: &mut Context, buf: &mut [u8])
+                          -> Poll<io::Result<(usize, net::SocketAddr)>> {
+        poll_read(&self.inner, cx, |s| s.recv_from(buf))
+    }
+
+    /// Receives data from the socket.
+    ///
+    /// On success, returns the number of bytes read and the address from
+    /// whence the data came.
+    pub fn recv_from<'a>(&'a self, buf: &'a mut [u8])
+                         -> impl Future<Output = io::Result<(usize, net::SocketAddr)>> + 'a {
+        future::poll_fn(move |cx| self.poll_recv_from(cx, buf))
+    }
+
+    /// Attempts to receive a datagram along with any file descriptors
+    /// attached to it, as `UnixDatagram::recv_from_with_fds` does.
+    pub fn poll_recv_from_with_fds(&self,
+                                   cx: &mut Context,
+                                   buf: &mut [u8],
+                                   fds: &mut Vec<OwnedFd>,
+                                   max_fds: usize)
+                                   -> Poll<io::Result<(usize, bool, UnixSocketAddr)>> {
+        poll_read(&self.inner, cx, |s| s.recv_from_with_fds(buf, fds, max_fds))
+    }
+
+    /// Receives a datagram along with any file descriptors attached to it, as
+    /// `UnixDatagram::recv_from_with_fds` does.
+    pub fn recv_from_with_fds<'a>(&'a self,
+                                  buf: &'a mut [u8],
+                                  fds: &'a mut Vec<OwnedFd>,
+                                  max_fds: usize)
+                                  -> impl Future<Output = io::Result<(usize, bool, UnixSocketAddr)>> + 'a {
+        future::poll_fn(move |cx| self.poll_recv_from_with_fds(cx, buf, fds, max_fds))
+    }
+
+    /// Attempts to send data on the socket to the socket's peer.
+    pub fn poll_send(&self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        poll_write(&self.inner, cx, |s| s.send(buf))
+    }
+
+    /// Sends data on the socket to the socket's peer.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = io::Result<usize>> + 'a {
+        future::poll_fn(move |cx| self.poll_send(cx, buf))
+    }
+
+    /// Attempts to send data on the socket to the specified address.
+    pub fn poll_send_to<P: AsRef<Path>>(&self, cx: &mut Context, buf: &[u8], path: P)
+                                        -> Poll<io::Result<usize>> {
+        poll_write(&self.inner, cx, |s| s.send_to(buf, path.as_ref()))
+    }
+
+    /// Sends data on the socket to the specified address.
+    ///
+    /// On success, returns the number of bytes written.
+    pub fn send_to<'a, P>(&'a self, buf: &'a [u8], path: P)
+                          -> impl Future<Output = io::Result<usize>> + 'a
+        where P: AsRef<Path> + 'a,
+    {
+        future::poll_fn(move |cx| self.poll_send_to(cx, buf, &path))
+    }
+
+    /// Attempts to send data along with a list of file descriptors to the
+    /// socket's peer, as `UnixDatagram::send_with_fds` does.
+    pub fn poll_send_with_fds(&self, cx: &mut Context, buf: &[u8], fds: &[RawFd])
+                              -> Poll<io::Result<usize>> {
+        poll_write(&self.inner, cx, |s| s.send_with_fds(buf, fds))
+    }
+
+    /// Sends data along with a list of file descriptors to the socket's
+    /// peer, as `UnixDatagram::send_with_fds` does.
+    pub fn send_with_fds<'a>(&'a self, buf: &'a [u8], fds: &'a [RawFd])
+                             -> impl Future<Output = io::Result<usize>> + 'a {
+        future::poll_fn(move |cx| self.poll_send_with_fds(cx, buf, fds))
+    }
+
+    /// Attempts to send data along with a list of file descriptors to the
+    /// specified address, as `UnixDatagram::send_to_with_fds` does.
+    pub fn poll_send_to_with_fds<P: AsRef<Path>>(&self,
+                                                 cx: &mut Context,
+                                                 buf: &[u8],
+                                                 fds: &[RawFd],
+                                                 path: P) -> Poll<io::Result<usize>> {
+        poll_write(&self.inner, cx, |s| s.send_to_with_fds(buf, fds, path.as_ref()))
+    }
+
+    /// Sends data along with a list of file descriptors to the specified
+    /// address, as `UnixDatagram::send_to_with_fds` does.
+    pub fn send_to_with_fds<'a, P>(&'a self, buf: &'a [u8], fds: &'a [RawFd], path: P)
+                                   -> impl Future<Output = io::Result<usize>> + 'a
+        where P: AsRef<Path> + 'a,
+    {
+        future::poll_fn(move |cx| self.poll_send_to_with_fds(cx, buf, fds, &path))
+    }
+}
+
+impl ::UnixStream {
+    /// Converts this stream into a `tokio::net::UnixStream`, registering it
+    /// with the current tokio runtime.
+    ///
+    /// This requires the `tokio` feature.
+    pub fn into_tokio(self) -> io::Result<tokio1::net::UnixStream> {
+        let stream = unsafe { net::UnixStream::from_raw_fd(self.into_raw_fd()) };
+        tokio1::net::UnixStream::from_std(stream)
+    }
+
+    /// Converts a `tokio::net::UnixStream` into a `UnixStream`, deregistering
+    /// it from its tokio runtime.
+    ///
+    /// This requires the `tokio` feature.
+    pub fn from_tokio(stream: tokio1::net::UnixStream) -> io::Result<::UnixStream> {
+        ::UnixStream::from_stream(stream.into_std()?)
+    }
+}
+
+impl ::UnixListener {
+    /// Converts this listener into a `tokio::net::UnixListener`, registering
+    /// it with the current tokio runtime.
+    ///
+    /// This requires the `tokio` feature.
+    pub fn into_tokio(self) -> io::Result<tokio1::net::UnixListener> {
+        let listener = unsafe { net::UnixListener::from_raw_fd(self.into_raw_fd()) };
+        tokio1::net::UnixListener::from_std(listener)
+    }
+
+    /// Converts a `tokio::net::UnixListener` into a `UnixListener`,
+    /// deregistering it from its tokio runtime.
+    ///
+    /// This requires the `tokio` feature.
+    pub fn from_tokio(listener: tokio1::net::UnixListener) -> io::Result<::UnixListener> {
+        ::UnixListener::from_listener(listener.into_std()?)
+    }
+}
+
+impl ::UnixDatagram {
+    /// Converts this socket into a `tokio::net::UnixDatagram`, registering
+    /// it with the current tokio runtime.
+    ///
+    /// This requires the `tokio` feature.
+    pub fn into_tokio(self) -> io::Result<tokio1::net::UnixDatagram> {
+        let socket = unsafe { net::UnixDatagram::from_raw_fd(self.into_raw_fd()) };
+        tokio1::net::UnixDatagram::from_std(socket)
+    }
+
+    /// Converts a `tokio::net::UnixDatagram` into a `UnixDatagram`,
+    /// deregistering it from its tokio runtime.
+    ///
+    /// This requires the `tokio` feature.
+    pub fn from_tokio(socket: tokio1::net::UnixDatagram) -> io::Result<::UnixDatagram> {
+        ::UnixDatagram::from_datagram(socket.into_std()?)
+    }
+}
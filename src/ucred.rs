@@ -0,0 +1,99 @@
+use std::io;
+
+use libc;
+
+use ancillary::{AncillaryData, SocketAncillary};
+use socket;
+
+/// Credentials of a process on the other end of a Unix socket.
+///
+/// These are reported for a connection by `UnixStream::peer_cred`, and can
+/// also be sent and received as `SCM_CREDENTIALS` control messages through
+/// the `ancillary` module.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UCred {
+    /// The process ID of the peer.
+    pub pid: libc::pid_t,
+    /// The user ID of the peer.
+    pub uid: libc::uid_t,
+    /// The group ID of the peer.
+    pub gid: libc::gid_t,
+}
+
+impl UCred {
+    /// Returns the credentials of the current process.
+    pub fn current() -> UCred {
+        unsafe {
+            UCred {
+                pid: libc::getpid(),
+                uid: libc::getuid(),
+                gid: libc::getgid(),
+            }
+        }
+    }
+}
+
+impl From<libc::ucred> for UCred {
+    fn from(cred: libc::ucred) -> UCred {
+        UCred {
+            pid: cred.pid,
+            uid: cred.uid,
+            gid: cred.gid,
+        }
+    }
+}
+
+impl From<UCred> for libc::ucred {
+    fn from(cred: UCred) -> libc::ucred {
+        libc::ucred {
+            pid: cred.pid,
+            uid: cred.uid,
+            gid: cred.gid,
+        }
+    }
+}
+
+/// Returns the credentials of the peer of the connected socket `fd`, as they
+/// were when the connection was established.
+pub fn peer_cred(fd: libc::c_int) -> io::Result<UCred> {
+    socket::getsockopt::<libc::ucred>(fd, libc::SOL_SOCKET, libc::SO_PEERCRED)
+        .map(UCred::from)
+}
+
+/// Sets the `SO_PASSCRED` option on the socket `fd`.
+pub fn set_passcred(fd: libc::c_int, passcred: bool) -> io::Result<()> {
+    socket::setsockopt(fd, libc::SOL_SOCKET, libc::SO_PASSCRED, passcred as libc::c_int)
+}
+
+/// Returns the value of the `SO_PASSCRED` option on the socket `fd`.
+pub fn passcred(fd: libc::c_int) -> io::Result<bool> {
+    socket::getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_PASSCRED)
+        .map(|v| v != 0)
+}
+
+/// Returns a buffer with room for the credentials attached to one message.
+pub fn cred_ancillary() -> SocketAncillary {
+    SocketAncillary::with_capacity(SocketAncillary::space_for_creds(1))
+}
+
+/// Returns a buffer holding `cred` to be attached to one message.
+pub fn send_cred_ancillary(cred: UCred) -> SocketAncillary {
+    let mut ancillary = cred_ancillary();
+    ancillary.add_creds(&[cred]);
+    ancillary
+}
+
+/// Returns the credentials received into `ancillary`, if any.
+///
+/// Any file descriptors the peer attached are closed, since they'd otherwise
+/// be leaked into this process.
+pub fn received_cred(ancillary: &mut SocketAncillary) -> Option<UCred> {
+    let cred = ancillary.messages().filter_map(|message| {
+        match message {
+            AncillaryData::ScmCredentials(mut creds) => creds.next(),
+            _ => None,
+        }
+    }).next();
+    ancillary.take_fds(&mut Vec::new(), 0);
+    cred
+}
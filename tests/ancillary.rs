@@ -0,0 +1,122 @@
+extern crate libc;
+extern crate mio_uds;
+
+use std::os::unix::prelude::*;
+
+use mio_uds::ancillary::{AncillaryData, SocketAncillary};
+use mio_uds::*;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {}", stringify!($e), e),
+    })
+}
+
+fn fds(ancillary: &SocketAncillary) -> Vec<RawFd> {
+    let mut fds = Vec::new();
+    for message in ancillary.messages() {
+        match message {
+            AncillaryData::ScmRights(rights) => fds.extend(rights),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+    fds
+}
+
+fn close(fds: Vec<RawFd>) {
+    for fd in fds {
+        unsafe {
+            libc::close(fd);
+        }
+    }
+}
+
+#[test]
+fn encode_decode() {
+    let mut ancillary = SocketAncillary::with_capacity(SocketAncillary::space_for_fds(2) +
+                                                       SocketAncillary::space_for_fds(1));
+    assert!(ancillary.is_empty());
+    assert!(ancillary.add_fds(&[1, 2]));
+    assert!(ancillary.add_fds(&[3]));
+    assert_eq!(ancillary.len(), ancillary.capacity());
+    assert!(!ancillary.add_fds(&[4]));
+    assert_eq!(fds(&ancillary), [1, 2, 3]);
+
+    ancillary.clear();
+    assert!(ancillary.is_empty());
+    assert_eq!(ancillary.messages().count(), 0);
+}
+
+#[test]
+fn stream_rights() {
+    let (a, b) = t!(UnixStream::pair());
+    let (c, _d) = t!(UnixStream::pair());
+
+    let mut ancillary = SocketAncillary::with_capacity(SocketAncillary::space_for_fds(2));
+    assert!(ancillary.add_fds(&[c.as_raw_fd(), c.as_raw_fd()]));
+    assert_eq!(t!(a.send_with_ancillary(b"x", &ancillary)), 1);
+
+    let mut buf = [0; 8];
+    let mut ancillary = SocketAncillary::with_capacity(128);
+    assert_eq!(t!(b.recv_with_ancillary(&mut buf, &mut ancillary)), 1);
+    assert!(!ancillary.truncated());
+    let received = fds(&ancillary);
+    assert_eq!(received.len(), 2);
+    for &fd in &received {
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+        assert!(flags & libc::FD_CLOEXEC != 0);
+    }
+    close(received);
+
+    assert!(a.send_with_ancillary(b"", &SocketAncillary::with_capacity(0)).is_ok());
+    ancillary.clear();
+    assert!(ancillary.add_fds(&[c.as_raw_fd()]));
+    assert!(a.send_with_ancillary(b"", &ancillary).is_err());
+}
+
+#[test]
+fn datagram_truncated() {
+    let (a, b) = t!(UnixDatagram::pair());
+    let mut ancillary = SocketAncillary::with_capacity(SocketAncillary::space_for_fds(8));
+    assert!(ancillary.add_fds(&[a.as_raw_fd(); 8]));
+    assert_eq!(t!(a.send_with_ancillary(b"hello", &ancillary)), 5);
+
+    let mut buf = [0; 8];
+    let mut ancillary = SocketAncillary::with_capacity(SocketAncillary::space_for_fds(1));
+    let (n, addr) = t!(b.recv_from_with_ancillary(&mut buf, &mut ancillary));
+    assert_eq!(&buf[..n], b"hello");
+    assert!(addr.is_unnamed());
+    assert!(ancillary.truncated());
+    let received = fds(&ancillary);
+    assert!(received.len() < 8);
+    close(received);
+
+    assert_eq!(t!(a.send(b"bye")), 3);
+    assert_eq!(t!(b.recv_with_ancillary(&mut buf, &mut ancillary)), 3);
+    assert!(!ancillary.truncated());
+    assert!(ancillary.is_empty());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn datagram_credentials() {
+    let (a, b) = t!(UnixDatagram::pair());
+    t!(b.set_passcred(true));
+
+    let mut ancillary = SocketAncillary::with_capacity(SocketAncillary::space_for_creds(1));
+    assert!(ancillary.add_creds(&[UCred::current()]));
+    assert_eq!(t!(a.send_with_ancillary(b"x", &ancillary)), 1);
+
+    let mut buf = [0; 8];
+    let mut ancillary = SocketAncillary::with_capacity(128);
+    assert_eq!(t!(b.recv_with_ancillary(&mut buf, &mut ancillary)), 1);
+    let mut creds = Vec::new();
+    for message in ancillary.messages() {
+        match message {
+            AncillaryData::ScmCredentials(c) => creds.extend(c),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+    assert_eq!(creds, [UCred::current()]);
+}
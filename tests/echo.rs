@@ -1,3 +1,5 @@
+#![cfg(feature = "mio-06")]
+
 extern crate mio;
 extern crate tempdir;
 extern crate mio_uds;
@@ -0,0 +1,58 @@
+#![cfg(feature = "mio-08")]
+
+extern crate mio08;
+extern crate tempdir;
+extern crate mio_uds;
+
+use std::io::prelude::*;
+use std::time::Duration;
+
+use mio08::{Events, Interest, Poll, Token};
+use mio_uds::*;
+use tempdir::TempDir;
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {}", stringify!($e), e),
+    })
+}
+
+#[test]
+fn register_sources() {
+    let mut poll = t!(Poll::new());
+    let mut events = Events::with_capacity(16);
+    let dir = t!(TempDir::new("mio-uds"));
+
+    let mut listener = t!(UnixListener::bind(dir.path().join("listener")));
+    t!(poll.registry().register(&mut listener, Token(0), Interest::READABLE));
+    let mut client = t!(UnixStream::connect(dir.path().join("listener")));
+    t!(poll.registry().register(&mut client, Token(1), Interest::WRITABLE));
+
+    t!(poll.poll(&mut events, Some(Duration::from_secs(1))));
+    let tokens = events.iter().map(|e| e.token()).collect::<Vec<_>>();
+    assert!(tokens.contains(&Token(0)));
+    assert!(tokens.contains(&Token(1)));
+
+    let (mut server, _) = t!(listener.accept()).unwrap();
+    t!(poll.registry().register(&mut server, Token(2), Interest::READABLE));
+    t!(client.write_all(b"hello"));
+    t!(poll.poll(&mut events, Some(Duration::from_secs(1))));
+    assert!(events.iter().any(|e| e.token() == Token(2) && e.is_readable()));
+    let mut buf = [0; 5];
+    t!(server.read_exact(&mut buf));
+    assert_eq!(&buf, b"hello");
+
+    let (mut a, b) = t!(UnixDatagram::pair());
+    t!(poll.registry().register(&mut a, Token(3), Interest::READABLE));
+    t!(b.send(b"ping"));
+    t!(poll.poll(&mut events, Some(Duration::from_secs(1))));
+    assert!(events.iter().any(|e| e.token() == Token(3) && e.is_readable()));
+    t!(poll.registry().deregister(&mut a));
+
+    let (mut c, d) = t!(UnixSeqpacket::pair());
+    t!(poll.registry().register(&mut c, Token(4), Interest::READABLE));
+    t!(d.send(b"pong"));
+    t!(poll.poll(&mut events, Some(Duration::from_secs(1))));
+    assert!(events.iter().any(|e| e.token() == Token(4) && e.is_readable()));
+}
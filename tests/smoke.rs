@@ -1,11 +1,19 @@
+#![cfg(feature = "mio-06")]
+
+#[cfg(feature = "iovec")]
 extern crate iovec;
+extern crate libc;
 extern crate mio;
 extern crate tempdir;
 extern crate mio_uds;
 
+use std::fs::{File, OpenOptions};
 use std::io::prelude::*;
+use std::io::{IoSlice, IoSliceMut, SeekFrom};
+use std::os::unix::prelude::*;
 use std::time::Duration;
 
+#[cfg(feature = "iovec")]
 use iovec::IoVec;
 use mio::*;
 use mio_uds::*;
@@ -67,6 +75,7 @@ fn stream() {
     assert_eq!(t!(b.read(&mut [0; 1024])), 1);
 }
 
+#[cfg(feature = "iovec")]
 #[test]
 fn stream_iovec() {
     let poll = t!(Poll::new());
@@ -99,3 +108,1075 @@ fn stream_iovec() {
     }
     assert_eq!(&send[..], &recv[..]);
 }
+
+#[test]
+fn stream_vectored() {
+    let (mut a, b) = t!(UnixStream::pair());
+
+    let send = b"Hello, World!";
+    let bufs = [IoSlice::new(&send[..6]), IoSlice::new(&send[6..])];
+    assert_eq!(t!(Write::write_vectored(&mut a, &bufs)), send.len());
+    assert_eq!(t!(Write::write_vectored(&mut &a, &bufs)), send.len());
+
+    let mut recv = [0; 26];
+    {
+        let (first, last) = recv.split_at_mut(6);
+        let mut bufs = [IoSliceMut::new(first), IoSliceMut::new(last)];
+        assert_eq!(t!(Read::read_vectored(&mut &b, &mut bufs)), 2 * send.len());
+    }
+    assert_eq!(&send[..], &recv[..13]);
+    assert_eq!(&send[..], &recv[13..]);
+
+    // The inherent methods only need a shared reference.
+    assert_eq!(t!(a.write_vectored(&bufs)), send.len());
+    let mut recv = [0; 13];
+    {
+        let (first, last) = recv.split_at_mut(6);
+        let mut bufs = [IoSliceMut::new(first), IoSliceMut::new(last)];
+        assert_eq!(t!(b.read_vectored(&mut bufs)), send.len());
+    }
+    assert_eq!(&send[..], &recv[..]);
+}
+
+#[test]
+fn stream_fds() {
+    let td = t!(TempDir::new("uds"));
+    let mut file = t!(OpenOptions::new().read(true).write(true).create(true).truncate(true)
+                      .open(td.path().join("foo")));
+    t!(file.write_all(b"hello"));
+
+    let (a, b) = t!(UnixStream::pair());
+    assert_eq!(t!(a.send_with_fds(b"x", &[file.as_raw_fd()])), 1);
+
+    let mut buf = [0; 16];
+    let mut fds = Vec::new();
+    assert_eq!(t!(b.recv_with_fds(&mut buf, &mut fds, 4)), (1, false));
+    assert_eq!(&buf[..1], b"x");
+    assert_eq!(fds.len(), 1);
+    let flags = unsafe { libc::fcntl(fds[0].as_raw_fd(), libc::F_GETFD) };
+    assert!(flags & libc::FD_CLOEXEC != 0);
+
+    let mut received = File::from(fds.pop().unwrap());
+    t!(received.seek(SeekFrom::Start(0)));
+    let mut contents = String::new();
+    t!(received.read_to_string(&mut contents));
+    assert_eq!(contents, "hello");
+
+    assert!(b.recv_with_fds(&mut buf, &mut fds, 4).is_err());
+}
+
+#[test]
+fn stream_fds_truncated() {
+    let (a, b) = t!(UnixStream::pair());
+    let (c, d) = t!(UnixStream::pair());
+    let fds = [c.as_raw_fd(), d.as_raw_fd(), c.as_raw_fd(), d.as_raw_fd()];
+    assert_eq!(t!(a.send_with_fds(b"x", &fds)), 1);
+
+    let mut buf = [0; 16];
+    let mut fds = Vec::new();
+    assert_eq!(t!(b.recv_with_fds(&mut buf, &mut fds, 1)), (1, true));
+    assert_eq!(fds.len(), 1);
+
+    assert!(a.send_with_fds(b"", &[c.as_raw_fd()]).is_err());
+}
+
+#[test]
+fn datagram_fds() {
+    let td = t!(TempDir::new("uds"));
+    let a = t!(UnixDatagram::bind(td.path().join("a")));
+    let b = t!(UnixDatagram::bind(td.path().join("b")));
+    let (c, d) = t!(UnixStream::pair());
+
+    let n = t!(a.send_to_with_fds(b"hello", &[c.as_raw_fd()], td.path().join("b")));
+    assert_eq!(n, 5);
+
+    let mut buf = [0; 16];
+    let mut fds = Vec::new();
+    let (n, truncated, addr) = t!(b.recv_from_with_fds(&mut buf, &mut fds, 4));
+    assert_eq!(&buf[..n], b"hello");
+    assert!(!truncated);
+    assert_eq!(addr.as_pathname(), Some(&*td.path().join("a")));
+    assert_eq!(fds.len(), 1);
+
+    let mut received = t!(UnixStream::from_stream(From::from(fds.pop().unwrap())));
+    assert_eq!(t!(received.write(b"x")), 1);
+    assert_eq!(t!((&d).read(&mut buf)), 1);
+
+    let (e, f) = t!(UnixDatagram::pair());
+    assert_eq!(t!(e.send_with_fds(b"", &[c.as_raw_fd(), d.as_raw_fd()])), 0);
+    let (n, truncated, addr) = t!(f.recv_from_with_fds(&mut buf, &mut fds, 1));
+    assert_eq!(n, 0);
+    assert!(truncated);
+    assert!(addr.is_unnamed());
+    assert_eq!(fds.len(), 1);
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn peer_cred() {
+    let td = t!(TempDir::new("uds"));
+    let listener = t!(UnixListener::bind(td.path().join("foo")));
+    let a = t!(UnixStream::connect(td.path().join("foo")));
+    let (b, _) = t!(listener.accept()).unwrap();
+
+    assert_eq!(t!(a.peer_cred()), UCred::current());
+    assert_eq!(t!(b.peer_cred()), UCred::current());
+
+    let (c, _d) = t!(UnixStream::pair());
+    assert_eq!(t!(c.peer_cred()), UCred::current());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn datagram_passcred() {
+    let td = t!(TempDir::new("uds"));
+    let a = t!(UnixDatagram::bind(td.path().join("a")));
+    let b = t!(UnixDatagram::bind(td.path().join("b")));
+    let mut buf = [0; 16];
+
+    assert!(!t!(b.passcred()));
+    assert_eq!(t!(a.send_to(b"foo", td.path().join("b"))), 3);
+    assert_eq!(t!(b.recv_with_cred(&mut buf)), (3, None));
+
+    t!(b.set_passcred(true));
+    assert!(t!(b.passcred()));
+    assert_eq!(t!(a.send_to(b"bar", td.path().join("b"))), 3);
+    let (n, addr, cred) = t!(b.recv_from_with_cred(&mut buf));
+    assert_eq!(&buf[..n], b"bar");
+    assert_eq!(addr.as_pathname(), Some(&*td.path().join("a")));
+    assert_eq!(cred, Some(UCred::current()));
+
+    let n = t!(a.send_to_with_cred(b"baz", UCred::current(), td.path().join("b")));
+    assert_eq!(n, 3);
+    assert_eq!(t!(b.recv_with_cred(&mut buf)), (3, Some(UCred::current())));
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn stream_passcred() {
+    let (a, b) = t!(UnixStream::pair());
+    let mut buf = [0; 16];
+
+    t!(b.set_passcred(true));
+    assert_eq!(t!(a.send_with_cred(b"foo", UCred::current())), 3);
+    assert_eq!(t!(b.recv_with_cred(&mut buf)), (3, Some(UCred::current())));
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn recv_with_cred_closes_fds() {
+    let mut buf = [0; 16];
+
+    // The passed end of a pair only reads as closed once every copy of the
+    // other end is closed, including the one received by the peer.
+    let (a, b) = t!(UnixStream::pair());
+    let (c, d) = t!(UnixStream::pair());
+    assert_eq!(t!(a.send_with_fds(b"foo", &[d.as_raw_fd()])), 3);
+    drop(d);
+    assert_eq!(t!(b.recv_with_cred(&mut buf)), (3, None));
+    assert_eq!(t!((&c).read(&mut buf)), 0);
+
+    let (a, b) = t!(UnixDatagram::pair());
+    let (c, d) = t!(UnixStream::pair());
+    assert_eq!(t!(a.send_with_fds(b"foo", &[d.as_raw_fd()])), 3);
+    assert_eq!(t!(a.send_with_fds(b"bar", &[d.as_raw_fd()])), 3);
+    drop(d);
+    t!(b.set_passcred(true));
+    assert_eq!(t!(b.recv_with_cred(&mut buf)).0, 3);
+    let (n, _, _) = t!(b.recv_from_with_cred(&mut buf));
+    assert_eq!(n, 3);
+    assert_eq!(t!((&c).read(&mut buf)), 0);
+}
+
+#[test]
+fn seqpacket() {
+    let td = t!(TempDir::new("uds"));
+    let listener = t!(UnixSeqpacketListener::bind(td.path().join("foo")));
+    assert!(t!(listener.accept()).is_none());
+    assert!(t!(listener.take_error()).is_none());
+
+    let poll = t!(Poll::new());
+    let mut events = Events::with_capacity(1024);
+    t!(poll.register(&listener, Token(1), Ready::readable(), PollOpt::edge()));
+
+    let a = t!(UnixSeqpacket::connect(td.path().join("foo")));
+    assert_eq!(t!(poll.poll(&mut events, None)), 1);
+    let (b, addr) = t!(listener.accept()).unwrap();
+    assert!(addr.is_unnamed());
+    assert_eq!(t!(a.peer_addr()), t!(listener.local_addr()));
+    assert_eq!(t!(b.local_addr()).as_pathname(), Some(&*td.path().join("foo")));
+
+    t!(poll.register(&b, Token(2), Ready::readable(), PollOpt::edge()));
+    assert_eq!(t!(a.send(b"hello")), 5);
+    assert_eq!(t!(a.send(b"world!")), 6);
+    assert_eq!(t!(poll.poll(&mut events, None)), 1);
+    assert_eq!(events.iter().next().unwrap().token(), Token(2));
+
+    let mut buf = [0; 16];
+    assert_eq!(t!(b.recv(&mut buf)), 5);
+    assert_eq!(&buf[..5], b"hello");
+    assert_eq!(b.recv(&mut buf[..3]).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(&buf[..3], b"wor");
+    assert_eq!(t!(a.send(b"again!")), 6);
+    assert_eq!(t!(b.recv_with_flags(&mut buf[..3], 0)), (3, libc::MSG_TRUNC));
+    assert_eq!(&buf[..3], b"aga");
+    assert!(b.recv(&mut buf).is_err());
+
+    let (c, d) = t!(UnixSeqpacket::pair());
+    let c2 = t!(c.try_clone());
+    assert_eq!(t!(c2.send(b"x")), 1);
+    drop((c, c2));
+    assert_eq!(t!(d.recv(&mut buf)), 1);
+    assert_eq!(t!(d.recv(&mut buf)), 0);
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn seqpacket_builder() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+    let listener = t!(UnixListenerBuilder::new().backlog(0).mode(0o600).bind_seqpacket(&path));
+    assert_eq!(t!(std::fs::metadata(&path)).permissions().mode() & 0o777, 0o600);
+
+    // Linux lets one more connection than the backlog through.
+    let _a = t!(UnixSeqpacket::connect(&path));
+    let err = UnixSeqpacket::connect(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert!(t!(listener.accept()).is_some());
+}
+
+#[test]
+fn unix_socket_addr() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+    let listener = t!(UnixListener::bind(&path));
+    let a = t!(UnixStream::connect(&path));
+    let (b, addr) = t!(listener.accept_unix_addr()).unwrap();
+
+    assert!(addr.is_unnamed());
+    assert_eq!(addr, UnixSocketAddr::unnamed());
+    assert_eq!(t!(listener.local_unix_addr()), t!(UnixSocketAddr::from_pathname(&path)));
+    assert_eq!(t!(a.peer_unix_addr()).as_pathname(), Some(&*path));
+    assert_eq!(t!(b.local_unix_addr()), UnixSocketAddr::from(t!(b.local_addr())));
+    assert_eq!(t!(a.local_unix_addr()).to_string(), "(unnamed)");
+    assert_eq!(t!(listener.local_unix_addr()).to_string(), path.display().to_string());
+
+    let dgram = t!(UnixDatagram::bind(td.path().join("bar")));
+    let client = t!(UnixDatagram::unbound());
+    assert_eq!(t!(client.send_to(b"hi", td.path().join("bar"))), 2);
+    let (n, from) = t!(dgram.recv_from_unix_addr(&mut [0; 8]));
+    assert_eq!(n, 2);
+    assert!(from.is_unnamed());
+
+    assert!(UnixSocketAddr::from_pathname("").is_err());
+    assert!(UnixSocketAddr::from_pathname(td.path().join("x".repeat(200))).is_err());
+    assert!("".parse::<UnixSocketAddr>().is_err());
+    let parsed: UnixSocketAddr = t!("/tmp/foo".parse());
+    assert_eq!(parsed.as_pathname(), Some(std::path::Path::new("/tmp/foo")));
+    let parsed: UnixSocketAddr = t!("./@foo".parse());
+    assert_eq!(parsed.as_pathname(), Some(std::path::Path::new("./@foo")));
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn abstract_addr() {
+    let addr = t!(UnixSocketAddr::from_abstract(b"mio-uds\0test"));
+    assert_eq!(addr.as_abstract(), Some(&b"mio-uds\0test"[..]));
+    assert!(addr.as_pathname().is_none());
+    assert_eq!(format!("{:?}", addr), "\"@mio-uds\\x00test\" (abstract)");
+
+    let addr: UnixSocketAddr = t!("@mio-uds-abstract".parse());
+    assert_eq!(addr.to_string(), "@mio-uds-abstract");
+    assert_eq!(addr.as_abstract(), Some(&b"mio-uds-abstract"[..]));
+    assert!(UnixSocketAddr::from_abstract(&[b'x'; 200]).is_err());
+
+    // Names which aren't printable UTF-8 are escaped, and parsed back.
+    let name = b"a\\b\0\xff\xc3\xa9\n";
+    let addr = t!(UnixSocketAddr::from_abstract(name));
+    assert_eq!(addr.to_string(), "@a\\\\b\\x00\\xff\u{e9}\\x0a");
+    let parsed: UnixSocketAddr = t!(addr.to_string().parse());
+    assert_eq!(parsed, addr);
+    assert!("@a\\b".parse::<UnixSocketAddr>().is_err());
+    assert!("@a\\x+f".parse::<UnixSocketAddr>().is_err());
+    assert!("@a\\x1".parse::<UnixSocketAddr>().is_err());
+
+    let name = format!("\0mio-uds-{}", std::process::id());
+    let listener = t!(UnixListener::bind(&name));
+    let expected = t!(UnixSocketAddr::from_abstract(&name.as_bytes()[1..]));
+    assert_eq!(t!(listener.local_unix_addr()), expected);
+    assert_eq!(UnixSocketAddr::from(t!(listener.local_addr())), expected);
+}
+
+#[test]
+fn addr_bind_connect() {
+    let td = t!(TempDir::new("uds"));
+    let addr = t!(UnixSocketAddr::from_pathname(td.path().join("foo")));
+    let listener = t!(UnixListener::bind_addr(&addr));
+    let a = t!(UnixStream::connect_addr(&addr));
+    let (b, _) = t!(listener.accept_unix_addr()).unwrap();
+    assert_eq!(t!(a.peer_unix_addr()), addr);
+    assert_eq!(t!(b.local_unix_addr()), addr);
+
+    let addr = t!(UnixSocketAddr::from_pathname(td.path().join("bar")));
+    let server = t!(UnixDatagram::bind_addr(&addr));
+    let client_addr = t!(UnixSocketAddr::from_pathname(td.path().join("baz")));
+    let client = t!(UnixDatagram::bind_addr(&client_addr));
+    assert_eq!(t!(client.send_to_addr(b"ping", &addr)), 4);
+    let mut buf = [0; 8];
+    let (n, from) = t!(server.recv_from_unix_addr(&mut buf));
+    assert_eq!(&buf[..n], b"ping");
+    assert_eq!(from, client_addr);
+    t!(server.connect_addr(&from));
+    assert_eq!(t!(server.send(b"pong")), 4);
+    assert_eq!(t!(client.recv(&mut buf)), 4);
+    assert_eq!(&buf[..4], b"pong");
+
+    let addr = t!(UnixSocketAddr::from_pathname(td.path().join("seq")));
+    let listener = t!(UnixSeqpacketListener::bind_addr(&addr));
+    let a = t!(UnixSeqpacket::connect_addr(&addr));
+    assert_eq!(t!(a.peer_addr()), addr);
+    assert!(t!(listener.accept()).is_some());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn abstract_send_to_addr() {
+    let id = std::process::id();
+    let addr = t!(UnixSocketAddr::from_abstract(format!("mio-uds-server-{}", id).as_bytes()));
+    let server = t!(UnixDatagram::bind_addr(&addr));
+    let client_addr = t!(UnixSocketAddr::from_abstract(format!("mio-uds-client-{}", id).as_bytes()));
+    let client = t!(UnixDatagram::bind_addr(&client_addr));
+
+    assert_eq!(t!(client.send_to_addr(b"ping", &addr)), 4);
+    let mut buf = [0; 8];
+    let (n, from) = t!(server.recv_from_unix_addr(&mut buf));
+    assert_eq!(&buf[..n], b"ping");
+    assert_eq!(from, client_addr);
+    assert_eq!(t!(server.send_to_addr(b"pong", &from)), 4);
+    assert_eq!(t!(client.recv(&mut buf)), 4);
+    assert_eq!(&buf[..4], b"pong");
+
+    let addr = t!(UnixSocketAddr::from_abstract(format!("mio-uds-stream-{}", id).as_bytes()));
+    let listener = t!(UnixListener::bind_addr(&addr));
+    let a = t!(UnixStream::connect_addr(&addr));
+    assert_eq!(t!(a.peer_unix_addr()), addr);
+    assert!(t!(listener.accept()).is_some());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn autobind() {
+    let (server, server_addr) = t!(UnixDatagram::autobind());
+    let (client, client_addr) = t!(UnixDatagram::autobind());
+    assert!(server_addr.as_abstract().is_some());
+    assert_ne!(server_addr, client_addr);
+    assert_eq!(t!(server.local_unix_addr()), server_addr);
+
+    assert_eq!(t!(client.send_to_addr(b"ping", &server_addr)), 4);
+    let mut buf = [0; 8];
+    let (n, from) = t!(server.recv_from_unix_addr(&mut buf));
+    assert_eq!(&buf[..n], b"ping");
+    assert_eq!(from, client_addr);
+    assert_eq!(t!(server.send_to_addr(b"pong", &from)), 4);
+    assert_eq!(t!(client.recv(&mut buf)), 4);
+    assert_eq!(&buf[..4], b"pong");
+
+    let td = t!(TempDir::new("uds"));
+    let listener = t!(UnixListener::bind(td.path().join("foo")));
+    let path = t!(UnixSocketAddr::from_pathname(td.path().join("foo")));
+    let (stream, local) = t!(UnixStream::autobind_connect(&path));
+    assert!(local.as_abstract().is_some());
+    assert_eq!(t!(stream.local_unix_addr()), local);
+    let (_, peer) = t!(listener.accept_unix_addr()).unwrap();
+    assert_eq!(peer, local);
+}
+
+#[test]
+fn listener_builder() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+    let gid = unsafe { libc::getgid() };
+    let listener = t!(UnixListenerBuilder::new()
+        .backlog(4)
+        .recv_buffer_size(64 * 1024)
+        .mode(0o660)
+        .owner(None, Some(gid))
+        .bind(&path));
+    let meta = t!(std::fs::metadata(&path));
+    assert_eq!(meta.permissions().mode() & 0o777, 0o660);
+    assert_eq!(meta.gid(), gid);
+
+    // Accepted streams get the listener's buffer sizes.
+    let size = t!(listener.recv_buffer_size());
+    let _a = t!(UnixStream::connect(&path));
+    let (b, _) = t!(listener.accept()).unwrap();
+    assert_eq!(t!(b.recv_buffer_size()), size);
+    let _a = t!(UnixStream::connect(&path));
+    let (b, _) = t!(listener.accept_unix_addr()).unwrap();
+    assert_eq!(t!(b.recv_buffer_size()), size);
+    let _a = t!(UnixStream::connect(&path));
+    let (b, _) = t!(t!(listener.try_clone()).accept()).unwrap();
+    assert_eq!(t!(b.recv_buffer_size()), size);
+
+    // A stale socket file is only replaced when asked to.
+    drop(listener);
+    assert!(UnixListener::bind(&path).is_err());
+    let listener = t!(UnixListenerBuilder::new().unlink_existing(true).bind(&path));
+    let _a = t!(UnixStream::connect(&path));
+    assert!(t!(listener.accept()).is_some());
+
+    // Other files are never removed.
+    let file = td.path().join("file");
+    t!(File::create(&file));
+    assert!(UnixListenerBuilder::new().unlink_existing(true).bind(&file).is_err());
+    assert!(file.exists());
+}
+
+#[test]
+fn datagram_builder() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+    let gid = unsafe { libc::getgid() };
+    let server = t!(UnixDatagramBuilder::new()
+        .mode(0o660)
+        .owner(None, Some(gid))
+        .bind(&path));
+    let meta = t!(std::fs::metadata(&path));
+    assert_eq!(meta.permissions().mode() & 0o777, 0o660);
+    assert_eq!(meta.gid(), gid);
+    assert_eq!(t!(server.local_unix_addr()).as_pathname(), Some(&*path));
+
+    let client = t!(UnixDatagram::unbound());
+    assert_eq!(t!(client.send_to(b"hi", &path)), 2);
+    assert_eq!(t!(server.recv(&mut [0; 8])), 2);
+
+    // The mode isn't subject to the umask.
+    let path = td.path().join("bar");
+    t!(UnixDatagramBuilder::new().mode(0o666).bind(&path));
+    let meta = t!(std::fs::metadata(&path));
+    assert_eq!(meta.permissions().mode() & 0o777, 0o666);
+
+    assert!(UnixDatagramBuilder::new().unlink_existing(true).mode(0o600).bind(&path).is_ok());
+
+    // Setting only the owner leaves the file with its usual mode.
+    let plain = td.path().join("plain");
+    t!(UnixDatagram::bind(&plain));
+    let path = td.path().join("owned");
+    t!(UnixDatagramBuilder::new().owner(None, Some(gid)).bind(&path));
+    let meta = t!(std::fs::metadata(&path));
+    assert_eq!(meta.permissions().mode(), t!(std::fs::metadata(&plain)).permissions().mode());
+    assert_eq!(meta.gid(), gid);
+}
+
+#[test]
+fn listener_builder_existing() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+    let _live = t!(UnixListener::bind(&path));
+
+    // Disabling one way of dealing with the file leaves the other alone.
+    let _live = t!(UnixListenerBuilder::new()
+        .unlink_existing(true)
+        .reclaim_stale(false)
+        .bind(&path));
+    assert!(UnixListenerBuilder::new()
+        .reclaim_stale(true)
+        .unlink_existing(false)
+        .bind(&path).is_err());
+    assert!(UnixListenerBuilder::new()
+        .unlink_existing(true)
+        .unlink_existing(false)
+        .bind(&path).is_err());
+
+    // Otherwise whichever was enabled last wins.
+    assert!(UnixListenerBuilder::new()
+        .unlink_existing(true)
+        .reclaim_stale(true)
+        .bind(&path).is_err());
+    let _live = t!(UnixListenerBuilder::new()
+        .reclaim_stale(true)
+        .unlink_existing(true)
+        .bind(&path));
+}
+
+#[test]
+fn datagram_builder_existing() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+    let _live = t!(UnixDatagram::bind(&path));
+
+    // Disabling one way of dealing with the file leaves the other alone.
+    let _live = t!(UnixDatagramBuilder::new()
+        .unlink_existing(true)
+        .reclaim_stale(false)
+        .bind(&path));
+    assert!(UnixDatagramBuilder::new()
+        .reclaim_stale(true)
+        .unlink_existing(false)
+        .bind(&path).is_err());
+    assert!(UnixDatagramBuilder::new()
+        .unlink_existing(true)
+        .unlink_existing(false)
+        .bind(&path).is_err());
+
+    // Otherwise whichever was enabled last wins.
+    assert!(UnixDatagramBuilder::new()
+        .unlink_existing(true)
+        .reclaim_stale(true)
+        .bind(&path).is_err());
+    let _live = t!(UnixDatagramBuilder::new()
+        .reclaim_stale(true)
+        .unlink_existing(true)
+        .bind(&path));
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn builder_abstract_mode() {
+    let addr = t!(UnixSocketAddr::from_abstract(format!("mio-uds-mode-{}", std::process::id()).as_bytes()));
+    assert!(UnixDatagramBuilder::new().mode(0o600).bind_addr(&addr).is_err());
+    assert!(UnixListenerBuilder::new().mode(0o600).bind_addr(&addr).is_err());
+    t!(UnixListenerBuilder::new().bind_addr(&addr));
+}
+
+#[test]
+fn bind_reclaiming() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+
+    // Nothing to reclaim.
+    let listener = t!(UnixListener::bind_reclaiming(&path));
+
+    // A live listener is left alone.
+    let err = UnixListener::bind_reclaiming(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+    let _a = t!(UnixStream::connect(&path));
+    assert!(t!(listener.accept()).is_some());
+
+    // A stale one is replaced.
+    drop(listener);
+    assert!(UnixListener::bind(&path).is_err());
+    let listener = t!(UnixListener::bind_reclaiming(&path));
+    let _a = t!(UnixStream::connect(&path));
+    assert!(t!(listener.accept()).is_some());
+
+    // Datagram sockets likewise.
+    let path = td.path().join("bar");
+    let dgram = t!(UnixDatagram::bind_reclaiming(&path));
+    let err = UnixDatagram::bind_reclaiming(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+    drop(dgram);
+    let dgram = t!(UnixDatagram::bind_reclaiming(&path));
+    t!(t!(UnixDatagram::unbound()).send_to(b"hi", &path));
+    assert_eq!(t!(dgram.recv(&mut [0; 8])), 2);
+
+    // Other files are never touched.
+    let file = td.path().join("file");
+    t!(File::create(&file));
+    let err = UnixListener::bind_reclaiming(&file).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+    assert!(t!(std::fs::symlink_metadata(&file)).is_file());
+}
+
+#[test]
+fn socket_file_guard() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+
+    let listener = t!(UnixListener::bind(&path));
+    let guard = t!(SocketFileGuard::for_socket(&listener));
+    assert_eq!(guard.path(), &*path);
+    drop(guard);
+    assert!(!path.exists());
+    drop(listener);
+
+    // A successor's socket file isn't removed.
+    let first = t!(UnixDatagram::bind(&path));
+    let guard = t!(SocketFileGuard::new(&path));
+    drop(first);
+    let second = t!(UnixDatagram::bind_reclaiming(&path));
+    drop(guard);
+    t!(std::fs::symlink_metadata(&path));
+    t!(t!(UnixDatagram::unbound()).send_to(b"hi", &path));
+    assert_eq!(t!(second.recv(&mut [0; 8])), 2);
+
+    let guard = t!(SocketFileGuard::new(&path));
+    guard.disarm();
+    t!(std::fs::symlink_metadata(&path));
+
+    let file = td.path().join("file");
+    t!(File::create(&file));
+    assert!(SocketFileGuard::new(&file).is_err());
+    assert!(SocketFileGuard::for_socket(&t!(UnixDatagram::unbound())).is_err());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn long_paths() {
+    let td = t!(TempDir::new("uds"));
+    let mut dir = td.path().to_path_buf();
+    for _ in 0..8 {
+        dir.push("a-rather-long-directory-name");
+    }
+    t!(std::fs::create_dir_all(&dir));
+    assert!(dir.as_os_str().len() > 200);
+
+    let path = dir.join("foo");
+    let listener = t!(UnixListener::bind(&path));
+    assert!(t!(std::fs::symlink_metadata(&path)).file_type().is_socket());
+    let mut a = t!(UnixStream::connect(&path));
+    let (mut b, _) = t!(listener.accept()).unwrap();
+    t!(a.write_all(b"hi"));
+    let mut buf = [0; 2];
+    t!(b.read_exact(&mut buf));
+    assert_eq!(&buf, b"hi");
+    // The address reported for the socket doesn't lead to the file.
+    assert!(SocketFileGuard::for_socket(&listener).is_err());
+    t!(SocketFileGuard::new(&path)).disarm();
+
+    let path = dir.join("bar");
+    let server = t!(UnixDatagram::bind(&path));
+    let client = t!(UnixDatagram::unbound());
+    assert_eq!(t!(client.send_to(b"hi", &path)), 2);
+    assert_eq!(t!(server.recv(&mut buf)), 2);
+    assert_eq!(t!(client.send_to_with_fds(b"hi", &[a.as_raw_fd()], &path)), 2);
+    let mut fds = Vec::new();
+    assert_eq!(t!(server.recv_from_with_fds(&mut buf, &mut fds, 1)).0, 2);
+    assert_eq!(fds.len(), 1);
+    t!(client.connect(&path));
+    assert_eq!(t!(client.send(b"hi")), 2);
+    assert_eq!(t!(server.recv(&mut buf)), 2);
+
+    let path = dir.join("seq");
+    let listener = t!(UnixSeqpacketListener::bind(&path));
+    let _a = t!(UnixSeqpacket::connect(&path));
+    assert!(t!(listener.accept()).is_some());
+
+    // Addresses with long paths work the same way.
+    let addr = t!(UnixSocketAddr::from_pathname(dir.join("addr")));
+    let listener = t!(UnixListener::bind_addr(&addr));
+    let _a = t!(UnixStream::connect_addr(&addr));
+    assert!(t!(listener.accept()).is_some());
+    let addr = t!(UnixSocketAddr::from_pathname(dir.join("dgram-addr")));
+    let server = t!(UnixDatagram::bind_addr(&addr));
+    assert_eq!(t!(client.send_to_addr(b"hi", &addr)), 2);
+    assert_eq!(t!(server.recv(&mut buf)), 2);
+    assert!(UnixSocketAddr::from_pathname(dir.join("x".repeat(200))).is_err());
+
+    let dir = t!(File::open(&dir));
+    let listener = t!(UnixListener::bind_at(&dir, "baz"));
+    let _a = t!(UnixStream::connect_at(&dir, "baz"));
+    assert!(t!(listener.accept()).is_some());
+
+    let server = t!(UnixDatagramBuilder::new().mode(0o600).bind_at(&dir, "quux"));
+    let client = t!(UnixDatagram::unbound());
+    t!(client.connect_at(&dir, "quux"));
+    assert_eq!(t!(client.send(b"hi")), 2);
+    assert_eq!(t!(server.recv(&mut buf)), 2);
+}
+
+#[test]
+fn socket_errors() {
+    let long = format!("/{}", "x".repeat(200));
+    let err = UnixDatagram::bind(&long).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    match SocketError::from_io(&err) {
+        Some(&SocketError::PathTooLong { ref path, len, max }) => {
+            assert_eq!(path.as_os_str(), &*long);
+            assert_eq!(len, 201);
+            assert!(max < len);
+        }
+        e => panic!("unexpected error {:?}", e),
+    }
+
+    let err = UnixStream::connect("/tmp/foo\0bar").unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(matches!(SocketError::from_io(&err), Some(&SocketError::InteriorNul { .. })));
+
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("missing");
+    let err = UnixStream::connect(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    let e = SocketError::from_io(&err).unwrap();
+    assert_eq!(e.raw_os_error(), Some(libc::ENOENT));
+    match *e {
+        SocketError::Syscall { syscall, ref addr, .. } => {
+            assert_eq!(syscall, "connect");
+            assert_eq!(addr.as_ref().and_then(|a| a.as_pathname()), Some(&*path));
+        }
+        ref e => panic!("unexpected error {:?}", e),
+    }
+    assert!(err.to_string().starts_with(&format!("connect on {} failed", path.display())));
+
+    let path = td.path().join("foo");
+    let _listener = t!(UnixListener::bind(&path));
+    let err = UnixListener::bind(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+    let e = SocketError::from_io(&err).unwrap();
+    assert_eq!(e.syscall(), Some("bind"));
+    assert_eq!(e.raw_os_error(), Some(libc::EADDRINUSE));
+    // The error reported by the system is the source of the returned one.
+    let source = std::error::Error::source(&err).and_then(|e| e.downcast_ref::<std::io::Error>());
+    assert_eq!(source.and_then(|e| e.raw_os_error()), Some(libc::EADDRINUSE));
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn backlog_full() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+    let listener = t!(UnixListenerBuilder::new().backlog(0).bind(&path));
+
+    // Linux lets one more connection than the backlog through.
+    let _a = t!(UnixStream::connect(&path));
+    let err = UnixStream::connect(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    let e = SocketError::from_io(&err).unwrap();
+    assert_eq!(e.syscall(), Some("connect"));
+    assert_eq!(e.raw_os_error(), Some(libc::EAGAIN));
+
+    let pending = t!(PendingConnect::new(&path));
+    let poll = t!(Poll::new());
+    t!(poll.register(&pending, Token(0), Ready::writable(), PollOpt::edge()));
+    let mut events = Events::with_capacity(8);
+    t!(poll.poll(&mut events, Some(Duration::from_millis(300))));
+    assert!(events.iter().next().is_none());
+    assert!(t!(pending.connected()).is_none());
+
+    // Accepting the first connection makes room for the pending one.
+    assert!(t!(listener.accept()).is_some());
+    t!(poll.poll(&mut events, Some(Duration::from_secs(5))));
+    assert_eq!(events.iter().next().map(|e| e.token()), Some(Token(0)));
+    let mut b = t!(pending.connected()).unwrap();
+    assert!(pending.connected().is_err());
+    t!(b.write_all(b"hi"));
+    let (mut c, _) = t!(listener.accept()).unwrap();
+    let mut buf = [0; 2];
+    t!(c.read_exact(&mut buf));
+    assert_eq!(&buf, b"hi");
+
+    // Connections which can be made straight away are ready immediately.
+    let pending = t!(PendingConnect::new(&path));
+    t!(poll.register(&pending, Token(1), Ready::writable(), PollOpt::edge()));
+    t!(poll.poll(&mut events, Some(Duration::from_secs(5))));
+    assert_eq!(events.iter().next().map(|e| e.token()), Some(Token(1)));
+    assert!(t!(pending.connected()).is_some());
+
+    // Connections waiting for room share a single thread, which exits once
+    // they've all been given up on.
+    let pending = (0..4).map(|_| t!(PendingConnect::new(&path))).collect::<Vec<_>>();
+    wait_for(|| connect_threads() == 1);
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(connect_threads(), 1);
+    drop(pending);
+    wait_for(|| connect_threads() == 0);
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn pending_connect_long_path() {
+    let td = t!(TempDir::new("uds"));
+    let mut path = td.path().to_path_buf();
+    for _ in 0..8 {
+        path.push("a-rather-long-directory-name");
+    }
+    t!(std::fs::create_dir_all(&path));
+    path.push("foo");
+    let listener = t!(UnixListenerBuilder::new().backlog(0).bind(&path));
+    let _a = t!(UnixStream::connect(&path));
+
+    // The directory the address goes through stays open while waiting, even
+    // if other files are opened in the meantime.
+    let pending = t!(PendingConnect::new(&path));
+    let _files = (0..8).map(|_| t!(File::open(td.path()))).collect::<Vec<_>>();
+    let poll = t!(Poll::new());
+    t!(poll.register(&pending, Token(0), Ready::writable(), PollOpt::edge()));
+    let mut events = Events::with_capacity(8);
+    t!(poll.poll(&mut events, Some(Duration::from_millis(100))));
+    assert!(t!(pending.connected()).is_none());
+
+    assert!(t!(listener.accept()).is_some());
+    t!(poll.poll(&mut events, Some(Duration::from_secs(5))));
+    assert_eq!(events.iter().next().map(|e| e.token()), Some(Token(0)));
+    let mut b = t!(pending.connected()).unwrap();
+    t!(b.write_all(b"hi"));
+    let (mut c, _) = t!(listener.accept()).unwrap();
+    let mut buf = [0; 2];
+    t!(c.read_exact(&mut buf));
+    assert_eq!(&buf, b"hi");
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn wait_for<F: FnMut() -> bool>(mut done: F) {
+    let start = std::time::Instant::now();
+    while !done() {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Returns the number of threads retrying `PendingConnect`s, which are
+/// named once they've started.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn connect_threads() -> usize {
+    t!(std::fs::read_dir("/proc/self/task")).filter(|task| {
+        let comm = std::fs::read_to_string(t!(task.as_ref()).path().join("comm"));
+        comm.map(|comm| comm.trim_end() == "mio-uds-connect").unwrap_or(false)
+    }).count()
+}
+
+#[test]
+fn blocking_helpers() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+    let listener = t!(UnixListener::bind(&path));
+
+    let err = listener.wait_readable(Some(Duration::from_millis(10))).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    let mut a = t!(UnixStream::connect_timeout(&path, Duration::from_secs(5)));
+    t!(listener.wait_readable(None));
+    let (mut b, _) = t!(listener.accept()).unwrap();
+
+    let err = b.wait_readable(Some(Duration::from_millis(10))).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    t!(a.wait_writable(Some(Duration::from_secs(5))));
+    t!(a.write_all(b"hi"));
+    t!(b.wait_readable(Some(Duration::from_secs(5))));
+    let mut buf = [0; 2];
+    t!(b.read_exact(&mut buf));
+
+    let dgram = t!(UnixDatagram::bind(td.path().join("bar")));
+    let err = dgram.wait_readable(Some(Duration::from_millis(10))).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    t!(dgram.wait_writable(None));
+
+    // Timeouts too long to represent are as good as none.
+    t!(dgram.wait_writable(Some(Duration::MAX)));
+    t!(UnixStream::connect_timeout(&path, Duration::MAX));
+
+    assert!(UnixStream::connect_timeout(&path, Duration::from_secs(0)).is_err());
+    assert!(UnixStream::connect_timeout(td.path().join("missing"), Duration::from_secs(1)).is_err());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn connect_timeout_backlog_full() {
+    let td = t!(TempDir::new("uds"));
+    let path = td.path().join("foo");
+    let _listener = t!(UnixListenerBuilder::new().backlog(0).bind(&path));
+    let _a = t!(UnixStream::connect(&path));
+
+    let err = UnixStream::connect_timeout(&path, Duration::from_millis(150)).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn buffer_options() {
+    let td = t!(TempDir::new("uds"));
+    let listener = t!(UnixListener::bind(td.path().join("foo")));
+    t!(listener.set_recv_buffer_size(64 * 1024));
+    assert!(t!(listener.recv_buffer_size()) >= 64 * 1024);
+
+    let (a, _b) = t!(UnixStream::pair());
+    t!(a.set_send_buffer_size(256 * 1024));
+    assert!(t!(a.send_buffer_size()) >= 128 * 1024);
+    t!(a.set_recv_buffer_size(32 * 1024));
+    assert!(t!(a.recv_buffer_size()) >= 16 * 1024);
+    t!(a.set_recv_low_water(16));
+    assert_eq!(t!(a.recv_low_water()), 16);
+    t!(a.send_low_water());
+
+    let (c, _d) = t!(UnixDatagram::pair());
+    t!(c.set_send_buffer_size(128 * 1024));
+    assert!(t!(c.send_buffer_size()) >= 64 * 1024);
+    t!(c.recv_buffer_size());
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn buffer_options_force() {
+    let (a, _b) = t!(UnixStream::pair());
+    // Only privileged processes may exceed the system-wide limit.
+    match a.set_send_buffer_size_force(8 * 1024 * 1024) {
+        Ok(()) => assert!(t!(a.send_buffer_size()) >= 8 * 1024 * 1024),
+        Err(e) => assert_eq!(e.raw_os_error(), Some(libc::EPERM)),
+    }
+}
+
+#[test]
+fn queue_sizes() {
+    let (mut a, b) = t!(UnixStream::pair());
+    assert_eq!(t!(b.bytes_available()), 0);
+    t!(a.write_all(&[0; 100]));
+    assert_eq!(t!(b.bytes_available()), 100);
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        assert!(t!(unsent_bytes(&a)) >= 100);
+    }
+    let mut buf = vec![0; t!(b.bytes_available())];
+    t!((&b).read_exact(&mut buf));
+    assert_eq!(t!(b.bytes_available()), 0);
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        assert_eq!(t!(unsent_bytes(&a)), 0);
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn unsent_bytes(s: &UnixStream) -> std::io::Result<usize> {
+    s.unsent_bytes()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn unsent_bytes(_s: &UnixStream) -> std::io::Result<usize> {
+    unreachable!()
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn next_datagram_size() {
+    let (a, b) = t!(UnixDatagram::pair());
+    assert_eq!(b.next_datagram_size().unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+    assert_eq!(t!(a.send(&[1; 3000])), 3000);
+    assert_eq!(t!(a.send(&[2; 10])), 10);
+    assert_eq!(t!(b.next_datagram_size()), 3000);
+    assert_eq!(t!(b.next_datagram_size()), 3000);
+    let mut buf = vec![0; t!(b.next_datagram_size())];
+    assert_eq!(t!(b.recv(&mut buf)), 3000);
+    assert_eq!(t!(b.next_datagram_size()), 10);
+}
+
+#[test]
+fn peek_and_flags() {
+    let (a, b) = t!(UnixStream::pair());
+    assert_eq!(t!(a.send_with_flags(b"hello", libc::MSG_NOSIGNAL)), 5);
+    let mut buf = [0; 3];
+    assert_eq!(t!(b.peek(&mut buf)), 3);
+    assert_eq!(&buf, b"hel");
+    let mut buf = [0; 10];
+    assert_eq!(t!(b.recv_with_flags(&mut buf, 0)), (5, 0));
+    assert_eq!(&buf[..5], b"hello");
+
+    let dir = t!(TempDir::new("mio-uds"));
+    let a = t!(UnixDatagram::bind(dir.path().join("a")));
+    let b = t!(UnixDatagram::bind(dir.path().join("b")));
+    assert_eq!(t!(a.send_to(b"datagram", dir.path().join("b"))), 8);
+    let mut buf = [0; 4];
+    assert_eq!(t!(b.peek(&mut buf)), 4);
+    let (n, addr) = t!(b.peek_from(&mut buf));
+    assert_eq!(n, 4);
+    assert_eq!(addr.as_pathname(), Some(&*dir.path().join("a")));
+    let (n, flags) = t!(b.recv_with_flags(&mut buf, 0));
+    assert_eq!(n, 4);
+    assert!(flags & libc::MSG_TRUNC != 0);
+    assert_eq!(&buf, b"data");
+    assert_eq!(b.recv_with_flags(&mut buf, 0).unwrap_err().kind(),
+               std::io::ErrorKind::WouldBlock);
+
+    // MSG_TRUNC makes the system report the full length of the datagram.
+    assert_eq!(t!(a.send_to(b"truncated", dir.path().join("b"))), 9);
+    let (n, flags) = t!(b.recv_with_flags(&mut buf, libc::MSG_TRUNC));
+    assert_eq!(n, 4);
+    assert!(flags & libc::MSG_TRUNC != 0);
+    assert_eq!(&buf[..n], b"trun");
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn batched_datagrams() {
+    let dir = t!(TempDir::new("mio-uds"));
+    let a = t!(UnixDatagram::bind(dir.path().join("a")));
+    let b = t!(UnixDatagram::bind(dir.path().join("b")));
+    let b_addr = t!(b.local_unix_addr());
+
+    let mut bufs = [[0; 4]; 4];
+    let mut meta = vec![RecvMeta::default(); 4];
+    {
+        let mut bufs = bufs.iter_mut().map(|b| &mut b[..]).collect::<Vec<_>>();
+        assert_eq!(b.recv_many(&mut bufs, &mut meta).unwrap_err().kind(),
+                   std::io::ErrorKind::WouldBlock);
+    }
+
+    let msgs: [&[u8]; 3] = [b"one", b"two", b"three"];
+    let addrs = vec![b_addr.clone(); 3];
+    assert_eq!(t!(a.send_many_to(&msgs, &addrs)), 3);
+    assert_eq!(a.send_many_to(&msgs, &addrs[..1]).unwrap_err().kind(),
+               std::io::ErrorKind::InvalidInput);
+    t!(a.connect_addr(&b_addr));
+    assert_eq!(t!(a.send_many(&[b"four"])), 1);
+
+    let mut recv_bufs = bufs.iter_mut().map(|b| &mut b[..]).collect::<Vec<_>>();
+    assert_eq!(t!(b.recv_many(&mut recv_bufs, &mut meta)), 4);
+    let a_path = dir.path().join("a");
+    assert_eq!(meta.iter().map(|m| m.len).collect::<Vec<_>>(), vec![3, 3, 4, 4]);
+    assert_eq!(meta.iter().map(|m| m.truncated).collect::<Vec<_>>(),
+               vec![false, false, true, false]);
+    assert!(meta.iter().all(|m| m.addr.as_pathname() == Some(&*a_path)));
+    assert_eq!(&bufs[0][..3], b"one");
+    assert_eq!(&bufs[2], b"thre");
+    assert_eq!(&bufs[3], b"four");
+}
+
+#[cfg(feature = "iovec")]
+#[test]
+fn datagram_bufs() {
+    let dir = t!(TempDir::new("mio-uds"));
+    let a = t!(UnixDatagram::bind(dir.path().join("a")));
+    let b = t!(UnixDatagram::bind(dir.path().join("b")));
+
+    let header: &IoVec = b"head"[..].into();
+    let body: &IoVec = b"body"[..].into();
+    assert_eq!(t!(a.send_to_bufs(&[header, body], dir.path().join("b"))), 8);
+    t!(a.connect(dir.path().join("b")));
+    assert_eq!(t!(a.send_bufs(&[body, header])), 8);
+
+    let mut first = [0; 2];
+    let mut second = [0; 10];
+    {
+        let mut bufs: [&mut IoVec; 2] = [(&mut first[..]).into(), (&mut second[..]).into()];
+        let (n, addr) = t!(b.recv_from_bufs(&mut bufs));
+        assert_eq!(n, 8);
+        assert_eq!(addr.as_pathname(), Some(&*dir.path().join("a")));
+    }
+    assert_eq!(&first, b"he");
+    assert_eq!(&second[..6], b"adbody");
+
+    let mut small = [0; 3];
+    {
+        let mut bufs: [&mut IoVec; 2] = [(&mut first[..]).into(), (&mut small[..]).into()];
+        assert_eq!(t!(b.recv_bufs(&mut bufs)), 5);
+    }
+    assert_eq!(&first, b"bo");
+    assert_eq!(&small, b"dyh");
+    assert_eq!(b.recv(&mut second).unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+}
+
+#[test]
+fn datagram_vectored() {
+    let dir = t!(TempDir::new("mio-uds"));
+    let a = t!(UnixDatagram::bind(dir.path().join("a")));
+    let b = t!(UnixDatagram::bind(dir.path().join("b")));
+
+    let bufs = [IoSlice::new(b"head"), IoSlice::new(b"body")];
+    assert_eq!(t!(a.send_to_vectored(&bufs, dir.path().join("b"))), 8);
+    t!(a.connect(dir.path().join("b")));
+    assert_eq!(t!(a.send_vectored(&bufs[1..])), 4);
+
+    let mut first = [0; 2];
+    let mut second = [0; 10];
+    {
+        let mut bufs = [IoSliceMut::new(&mut first), IoSliceMut::new(&mut second)];
+        let (n, addr) = t!(b.recv_from_vectored(&mut bufs));
+        assert_eq!(n, 8);
+        assert_eq!(addr.as_pathname(), Some(&*dir.path().join("a")));
+    }
+    assert_eq!(&first, b"he");
+    assert_eq!(&second[..6], b"adbody");
+
+    let mut small = [0; 1];
+    {
+        let mut bufs = [IoSliceMut::new(&mut first), IoSliceMut::new(&mut small)];
+        assert_eq!(t!(b.recv_vectored(&mut bufs)), 3);
+    }
+    assert_eq!(&first, b"bo");
+    assert_eq!(&small, b"d");
+    assert_eq!(b.recv(&mut second).unwrap_err().kind(), std::io::ErrorKind::WouldBlock);
+}
@@ -0,0 +1,129 @@
+#![cfg(feature = "tokio")]
+
+extern crate tempdir;
+extern crate tokio1;
+extern crate mio_uds;
+
+use std::fs::File;
+use std::future;
+use std::io::prelude::*;
+use std::os::unix::prelude::*;
+use std::pin::Pin;
+
+use tempdir::TempDir;
+use tokio1::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio1::runtime::{Builder, Runtime};
+
+macro_rules! t {
+    ($e:expr) => (match $e {
+        Ok(e) => e,
+        Err(e) => panic!("{} failed with {}", stringify!($e), e),
+    })
+}
+
+fn runtime() -> Runtime {
+    t!(Builder::new_current_thread().enable_io().build())
+}
+
+#[test]
+fn stream() {
+    let rt = runtime();
+    let _guard = rt.enter();
+    let (a, b) = t!(mio_uds::UnixStream::pair());
+    let mut a = t!(mio_uds::tokio::UnixStream::new(a));
+    let mut b = t!(mio_uds::tokio::UnixStream::new(b));
+
+    let n = t!(rt.block_on(future::poll_fn(|cx| Pin::new(&mut a).poll_write(cx, b"hello"))));
+    assert_eq!(n, 5);
+    let mut buf = [0; 10];
+    let mut read_buf = ReadBuf::new(&mut buf);
+    t!(rt.block_on(future::poll_fn(|cx| Pin::new(&mut b).poll_read(cx, &mut read_buf))));
+    assert_eq!(read_buf.filled(), b"hello");
+
+    let file = t!(File::open("/dev/null"));
+    assert_eq!(t!(rt.block_on(b.send_with_fds(b"x", &[file.as_raw_fd()]))), 1);
+    let mut fds = Vec::new();
+    assert_eq!(t!(rt.block_on(a.recv_with_fds(&mut buf, &mut fds, 1))), (1, false));
+    assert_eq!(fds.len(), 1);
+
+    t!(rt.block_on(future::poll_fn(|cx| Pin::new(&mut a).poll_shutdown(cx))));
+    let mut read_buf = ReadBuf::new(&mut buf);
+    t!(rt.block_on(future::poll_fn(|cx| Pin::new(&mut b).poll_read(cx, &mut read_buf))));
+    assert!(read_buf.filled().is_empty());
+}
+
+#[test]
+fn listener_accept() {
+    let rt = runtime();
+    let _guard = rt.enter();
+    let dir = t!(TempDir::new("mio-uds"));
+    let path = dir.path().join("sock");
+    let listener = t!(mio_uds::UnixListener::bind(&path));
+    let listener = t!(mio_uds::tokio::UnixListener::new(listener));
+
+    let mut client = t!(mio_uds::UnixStream::connect(&path));
+    let (server, _) = t!(rt.block_on(listener.accept()));
+    t!(client.write_all(b"hi"));
+    let mut buf = [0; 2];
+    t!(server.get_ref().read_exact(&mut buf));
+    assert_eq!(&buf, b"hi");
+}
+
+#[test]
+fn datagram() {
+    let rt = runtime();
+    let _guard = rt.enter();
+    let dir = t!(TempDir::new("mio-uds"));
+    let a = t!(mio_uds::UnixDatagram::bind(dir.path().join("a")));
+    let b = t!(mio_uds::UnixDatagram::bind(dir.path().join("b")));
+    let a = t!(mio_uds::tokio::UnixDatagram::new(a));
+    let b = t!(mio_uds::tokio::UnixDatagram::new(b));
+
+    assert_eq!(t!(rt.block_on(a.send_to(b"hello", dir.path().join("b")))), 5);
+    let mut buf = [0; 10];
+    let (n, addr) = t!(rt.block_on(b.recv_from(&mut buf)));
+    assert_eq!(&buf[..n], b"hello");
+    assert_eq!(addr.as_pathname(), Some(&*dir.path().join("a")));
+
+    let file = t!(File::open("/dev/null"));
+    let fds = [file.as_raw_fd()];
+    assert_eq!(t!(rt.block_on(b.send_to_with_fds(b"x", &fds, dir.path().join("a")))), 1);
+    let mut received = Vec::new();
+    let (n, truncated, _) = t!(rt.block_on(a.recv_from_with_fds(&mut buf, &mut received, 1)));
+    assert_eq!((n, truncated, received.len()), (1, false, 1));
+
+    t!(a.get_ref().connect(dir.path().join("b")));
+    assert_eq!(t!(rt.block_on(a.send_with_fds(b"y", &fds))), 1);
+    assert_eq!(t!(rt.block_on(a.send(b"z"))), 1);
+    assert_eq!(t!(rt.block_on(b.recv(&mut buf))), 1);
+    assert_eq!(t!(rt.block_on(b.recv(&mut buf))), 1);
+    assert_eq!(&buf[..1], b"z");
+}
+
+#[test]
+fn convert() {
+    let rt = runtime();
+    let _guard = rt.enter();
+    let dir = t!(TempDir::new("mio-uds"));
+
+    let (a, _b) = t!(mio_uds::UnixStream::pair());
+    let fd = a.as_raw_fd();
+    let a = t!(a.into_tokio());
+    assert_eq!(a.as_raw_fd(), fd);
+    let a = t!(mio_uds::UnixStream::from_tokio(a));
+    assert_eq!(a.as_raw_fd(), fd);
+
+    let listener = t!(mio_uds::UnixListener::bind(dir.path().join("sock")));
+    let fd = listener.as_raw_fd();
+    let listener = t!(listener.into_tokio());
+    assert_eq!(t!(listener.local_addr()).as_pathname(), Some(&*dir.path().join("sock")));
+    let listener = t!(mio_uds::UnixListener::from_tokio(listener));
+    assert_eq!(listener.as_raw_fd(), fd);
+
+    let (a, _b) = t!(mio_uds::UnixDatagram::pair());
+    let fd = a.as_raw_fd();
+    let a = t!(a.into_tokio());
+    assert_eq!(a.as_raw_fd(), fd);
+    let a = t!(mio_uds::UnixDatagram::from_tokio(a));
+    assert_eq!(a.as_raw_fd(), fd);
+}